@@ -523,7 +523,7 @@ pub extern "system" fn librustzcash_sapling_ka_agree(
     };
 
     // Compute key agreement
-    let ka = sapling_ka_agree(&sk, &p);
+    let ka = sapling_ka_agree(&sk, &p, &JUBJUB);
 
     // Produce result
     let result = unsafe { &mut *result };
@@ -8,7 +8,7 @@ extern crate sapling_crypto;
 use rand::{Rand, thread_rng};
 use pairing::bls12_381::Bls12;
 use sapling_crypto::jubjub::JubjubBls12;
-use sapling_crypto::pedersen_hash::{pedersen_hash, Personalization};
+use sapling_crypto::pedersen_hash::{pedersen_hash, PedersenHasher, Personalization};
 
 #[bench]
 fn bench_pedersen_hash(b: &mut test::Bencher) {
@@ -21,3 +21,33 @@ fn bench_pedersen_hash(b: &mut test::Bencher) {
         pedersen_hash::<Bls12, _>(personalization, bits.clone(), &params)
     });
 }
+
+#[bench]
+fn bench_pedersen_hash_batch(b: &mut test::Bencher) {
+    let params = JubjubBls12::new();
+    let rng = &mut thread_rng();
+    let inputs = (0..100)
+        .map(|_| (0..510).map(|_| bool::rand(rng)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let personalization = Personalization::MerkleTree(31);
+
+    b.iter(|| {
+        inputs.iter()
+            .map(|bits| pedersen_hash::<Bls12, _>(personalization, bits.iter().cloned(), &params).into_xy())
+            .collect::<Vec<_>>()
+    });
+}
+
+#[bench]
+fn bench_pedersen_hasher_hash_many(b: &mut test::Bencher) {
+    let params = JubjubBls12::new();
+    let rng = &mut thread_rng();
+    let inputs = (0..100)
+        .map(|_| (0..510).map(|_| bool::rand(rng)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let hasher = PedersenHasher::<Bls12>::new(Personalization::MerkleTree(31), &params);
+
+    b.iter(|| {
+        hasher.hash_many(inputs.iter().map(|bits| bits.iter().cloned()))
+    });
+}
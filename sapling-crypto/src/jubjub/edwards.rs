@@ -357,6 +357,41 @@ impl<E: JubjubEngine, Subgroup> Point<E, Subgroup> {
         (x, y)
     }
 
+    /// Converts each of the given points to affine coordinates, using a
+    /// single field inversion for the whole batch (Montgomery's trick)
+    /// instead of one inversion per point as with `into_xy`.
+    pub fn batch_into_xy(points: &[Self]) -> Vec<(E::Fr, E::Fr)>
+    {
+        // prods[i] = z_0 * ... * z_{i-1}
+        let mut acc = E::Fr::one();
+        let prods: Vec<E::Fr> = points.iter().map(|p| {
+            let prod = acc;
+            acc.mul_assign(&p.z);
+            prod
+        }).collect();
+
+        // z is never zero for a point on the curve
+        let mut inv = acc.inverse().unwrap();
+
+        let mut result = vec![(E::Fr::zero(), E::Fr::zero()); points.len()];
+        for ((p, prod), xy) in points.iter().zip(prods).zip(result.iter_mut()).rev() {
+            // inv = 1 / (z_0 * ... * z_i), so zinv = 1 / z_i
+            let mut zinv = inv;
+            zinv.mul_assign(&prod);
+            inv.mul_assign(&p.z);
+
+            let mut x = p.x;
+            x.mul_assign(&zinv);
+
+            let mut y = p.y;
+            y.mul_assign(&zinv);
+
+            *xy = (x, y);
+        }
+
+        result
+    }
+
     #[must_use]
     pub fn negate(&self) -> Self {
         let mut p = self.clone();
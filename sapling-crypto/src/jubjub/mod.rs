@@ -127,8 +127,14 @@ pub trait JubjubParams<E: JubjubEngine>: Sized {
     /// fixed generator.
     fn circuit_generators(&self, FixedGenerators) -> &[Vec<(E::Fr, E::Fr)>];
     /// Returns the window size for exponentiation of Pedersen hash generators
-    /// outside the circuit
-    fn pedersen_hash_exp_window_size() -> u32;
+    /// outside the circuit. A window size of zero means that no exp table is
+    /// kept, and the generators are exponentiated on demand.
+    ///
+    /// This used to be an associated function without a receiver, which fixed
+    /// the window size for every instance of the parameters. It now takes
+    /// `&self`, so implementations of `JubjubParams` and callers of
+    /// `P::pedersen_hash_exp_window_size()` must be updated.
+    fn pedersen_hash_exp_window_size(&self) -> u32;
 }
 
 impl JubjubEngine for Bls12 {
@@ -148,6 +154,8 @@ pub struct JubjubBls12 {
 
     fixed_base_generators: Vec<edwards::Point<Bls12, PrimeOrder>>,
     fixed_base_circuit_generators: Vec<Vec<Vec<(Fr, Fr)>>>,
+
+    pedersen_hash_exp_window_size: u32,
 }
 
 impl JubjubParams<Bls12> for JubjubBls12 {
@@ -178,13 +186,32 @@ impl JubjubParams<Bls12> for JubjubBls12 {
     {
         &self.fixed_base_circuit_generators[base as usize][..]
     }
-    fn pedersen_hash_exp_window_size() -> u32 {
-        8
+    fn pedersen_hash_exp_window_size(&self) -> u32 {
+        self.pedersen_hash_exp_window_size
     }
 }
 
 impl JubjubBls12 {
+    /// The window size used by `JubjubBls12::new` for the Pedersen hash exp table.
+    pub const DEFAULT_PEDERSEN_HASH_EXP_WINDOW_SIZE: u32 = 8;
+
     pub fn new() -> Self {
+        Self::with_pedersen_hash_exp_window_size(Self::DEFAULT_PEDERSEN_HASH_EXP_WINDOW_SIZE)
+    }
+
+    /// Constructs the parameters with a Pedersen hash exp table of the given window
+    /// size, trading memory for speed of Pedersen hashes outside the circuit.
+    ///
+    /// The exp table holds `2^window` points per window, so it dominates the memory
+    /// usage of the parameters at the default size of 8. Passing zero disables the
+    /// table entirely, and the generators are then exponentiated on demand.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is greater than 16.
+    pub fn with_pedersen_hash_exp_window_size(window: u32) -> Self {
+        assert!(window <= 16, "Pedersen hash exp window size is too large");
+
         let montgomery_a = Fr::from_str("40962").unwrap();
         let mut montgomery_2a = montgomery_a;
         montgomery_2a.double();
@@ -205,6 +232,8 @@ impl JubjubBls12 {
             pedersen_circuit_generators: vec![],
             fixed_base_generators: vec![],
             fixed_base_circuit_generators: vec![],
+
+            pedersen_hash_exp_window_size: window,
         };
 
         fn find_group_hash<E: JubjubEngine>(
@@ -270,14 +299,12 @@ impl JubjubBls12 {
         }
 
         // Create the exp table for the Pedersen hash generators
-        {
+        if window > 0 {
             let mut pedersen_hash_exp = vec![];
 
             for g in &tmp_params.pedersen_hash_generators {
                 let mut g = g.clone();
 
-                let window = JubjubBls12::pedersen_hash_exp_window_size();
-
                 let mut tables = vec![];
 
                 let mut num_bits = 0;
@@ -402,6 +429,34 @@ impl JubjubBls12 {
 
         tmp_params
     }
+
+    /// Returns the approximate number of bytes of heap memory used by the
+    /// pre-computed tables in these parameters.
+    pub fn memory_usage(&self) -> usize {
+        use std::mem::size_of;
+
+        fn nested_len<T>(v: &[Vec<T>]) -> usize {
+            v.iter().map(|inner| inner.len()).sum()
+        }
+
+        let point_size = size_of::<edwards::Point<Bls12, PrimeOrder>>();
+        let coeff_size = size_of::<(Fr, Fr)>();
+
+        let pedersen_hash_exp: usize = self.pedersen_hash_exp.iter()
+            .map(|tables| nested_len(tables))
+            .sum();
+        let pedersen_circuit_generators: usize = self.pedersen_circuit_generators.iter()
+            .map(|windows| nested_len(windows))
+            .sum();
+        let fixed_base_circuit_generators: usize = self.fixed_base_circuit_generators.iter()
+            .map(|windows| nested_len(windows))
+            .sum();
+
+        (self.pedersen_hash_generators.len()
+            + pedersen_hash_exp
+            + self.fixed_base_generators.len()) * point_size
+            + (pedersen_circuit_generators + fixed_base_circuit_generators) * coeff_size
+    }
 }
 
 #[test]
@@ -29,75 +29,220 @@ pub fn pedersen_hash<E, I>(
     where I: IntoIterator<Item=bool>,
           E: JubjubEngine
 {
-    let mut bits = personalization.get_bits().into_iter().chain(bits.into_iter());
+    PedersenHasher::<E>::new(personalization, params).hash(bits)
+}
 
-    let mut result = edwards::Point::zero();
-    let mut generators = params.pedersen_hash_exp_table().iter();
+/// The scalar being accumulated for one segment of a Pedersen hash.
+struct SegmentState<E: JubjubEngine> {
+    acc: E::Fs,
+    cur: E::Fs,
+    chunks_remaining: usize,
+}
 
-    loop {
-        let mut acc = E::Fs::zero();
-        let mut cur = E::Fs::one();
-        let mut chunks_remaining = params.pedersen_hash_chunks_per_generator();
-        let mut encountered_bits = false;
+impl<E: JubjubEngine> Clone for SegmentState<E> {
+    fn clone(&self) -> Self {
+        SegmentState {
+            acc: self.acc,
+            cur: self.cur,
+            chunks_remaining: self.chunks_remaining,
+        }
+    }
+}
 
-        // Grab three bits from the input
-        while let Some(a) = bits.next() {
-            encountered_bits = true;
+impl<E: JubjubEngine> SegmentState<E> {
+    fn new(params: &E::Params) -> Self {
+        SegmentState {
+            acc: E::Fs::zero(),
+            cur: E::Fs::one(),
+            chunks_remaining: params.pedersen_hash_chunks_per_generator(),
+        }
+    }
 
-            let b = bits.next().unwrap_or(false);
-            let c = bits.next().unwrap_or(false);
+    /// Adds a three-bit chunk to the scalar. Returns true if the segment is
+    /// now full.
+    fn push_chunk(&mut self, a: bool, b: bool, c: bool) -> bool {
+        // Start computing this portion of the scalar
+        let mut tmp = self.cur;
+        if a {
+            tmp.add_assign(&self.cur);
+        }
+        self.cur.double(); // 2^1 * cur
+        if b {
+            tmp.add_assign(&self.cur);
+        }
 
-            // Start computing this portion of the scalar
-            let mut tmp = cur;
-            if a {
-                tmp.add_assign(&cur);
-            }
-            cur.double(); // 2^1 * cur
-            if b {
-                tmp.add_assign(&cur);
-            }
+        // conditionally negate
+        if c {
+            tmp.negate();
+        }
 
-            // conditionally negate
-            if c {
-                tmp.negate();
-            }
+        self.acc.add_assign(&tmp);
 
-            acc.add_assign(&tmp);
+        self.chunks_remaining -= 1;
 
-            chunks_remaining -= 1;
+        if self.chunks_remaining == 0 {
+            true
+        } else {
+            self.cur.double(); // 2^2 * cur
+            self.cur.double(); // 2^3 * cur
+            self.cur.double(); // 2^4 * cur
+            false
+        }
+    }
+}
 
-            if chunks_remaining == 0 {
-                break;
-            } else {
-                cur.double(); // 2^2 * cur
-                cur.double(); // 2^3 * cur
-                cur.double(); // 2^4 * cur
-            }
+/// A Pedersen hash context for a single personalization.
+///
+/// `pedersen_hash` processes the personalization bits on every call. A
+/// `PedersenHasher` absorbs them once, and then only processes the input bits
+/// of each hash, which makes it cheaper to hash many inputs under the same
+/// personalization (such as all the nodes at one depth of a Merkle tree).
+pub struct PedersenHasher<'a, E: JubjubEngine> {
+    params: &'a E::Params,
+    prefix: SegmentState<E>,
+}
+
+impl<'a, E: JubjubEngine> PedersenHasher<'a, E> {
+    pub fn new(personalization: Personalization, params: &'a E::Params) -> Self {
+        let bits = personalization.get_bits();
+
+        // Personalizations are a whole number of chunks, so they never
+        // leave a partial chunk to be completed by the input.
+        assert_eq!(bits.len() % 3, 0);
+
+        let mut prefix = SegmentState::new(params);
+        for chunk in bits.chunks(3) {
+            assert!(!prefix.push_chunk(chunk[0], chunk[1], chunk[2]));
         }
 
-        if !encountered_bits {
-            break;
+        PedersenHasher {
+            params,
+            prefix,
         }
+    }
+
+    /// Computes the Pedersen hash of the given bits.
+    pub fn hash<I>(&self, bits: I) -> edwards::Point<E, PrimeOrder>
+        where I: IntoIterator<Item=bool>
+    {
+        let params = self.params;
+        let mut bits = bits.into_iter();
 
-        let mut table: &[Vec<edwards::Point<E, _>>] = &generators.next().expect("we don't have enough generators");
-        let window = JubjubBls12::pedersen_hash_exp_window_size();
-        let window_mask = (1 << window) - 1;
+        let mut result = edwards::Point::zero();
+        let mut generators = params.pedersen_hash_generators().iter();
+        let mut exp_tables = params.pedersen_hash_exp_table().iter();
 
-        let mut acc = acc.into_repr();
-        
-        let mut tmp = edwards::Point::zero();
+        // The first segment always contains the personalization.
+        let mut segment = self.prefix.clone();
+        let mut encountered_bits = true;
 
-        while !acc.is_zero() {
-            let i = (acc.as_ref()[0] & window_mask) as usize;
+        loop {
+            // Grab three bits from the input
+            while let Some(a) = bits.next() {
+                encountered_bits = true;
 
-            tmp = tmp.add(&table[0][i], params);
+                let b = bits.next().unwrap_or(false);
+                let c = bits.next().unwrap_or(false);
 
-            acc.shr(window);
-            table = &table[1..];
+                if segment.push_chunk(a, b, c) {
+                    break;
+                }
+            }
+
+            if !encountered_bits {
+                break;
+            }
+
+            let generator = generators.next().expect("we don't have enough generators");
+            let tmp = exp_segment(segment.acc, generator, exp_tables.next(), params);
+            result = result.add(&tmp, params);
+
+            segment = SegmentState::new(params);
+            encountered_bits = false;
         }
 
-        result = result.add(&tmp, params);
+        result
+    }
+
+    /// Computes the Pedersen hashes of each of the given inputs, in affine
+    /// coordinates.
+    ///
+    /// The hashes are accumulated in extended coordinates and then normalized
+    /// together with a single field inversion, which is cheaper than calling
+    /// `hash(bits).into_xy()` for each input.
+    pub fn hash_many<I, J>(&self, inputs: I) -> Vec<(E::Fr, E::Fr)>
+        where I: IntoIterator<Item=J>,
+              J: IntoIterator<Item=bool>
+    {
+        let hashes: Vec<_> = inputs.into_iter().map(|bits| self.hash(bits)).collect();
+
+        edwards::Point::batch_into_xy(&hashes)
+    }
+}
+
+/// Computes `[acc] generator`, using the generator's exp table if one has been
+/// pre-computed.
+fn exp_segment<E: JubjubEngine>(
+    acc: E::Fs,
+    generator: &edwards::Point<E, PrimeOrder>,
+    table: Option<&Vec<Vec<edwards::Point<E, PrimeOrder>>>>,
+    params: &E::Params
+) -> edwards::Point<E, PrimeOrder>
+{
+    match table {
+        Some(table) => {
+            let mut table: &[Vec<edwards::Point<E, _>>] = table;
+            let window = params.pedersen_hash_exp_window_size();
+            let window_mask = (1 << window) - 1;
+
+            let mut acc = acc.into_repr();
+
+            let mut tmp = edwards::Point::zero();
+
+            while !acc.is_zero() {
+                let i = (acc.as_ref()[0] & window_mask) as usize;
+
+                tmp = tmp.add(&table[0][i], params);
+
+                acc.shr(window);
+                table = &table[1..];
+            }
+
+            tmp
+        },
+        // No exp table was pre-computed, so exponentiate on demand
+        None => generator.mul(acc, params)
     }
+}
 
-    result
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pairing::bls12_381::Bls12;
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_pedersen_hasher_hash_many() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+            0xe5,
+        ]);
+        let params = &JubjubBls12::new();
+
+        for personalization in &[Personalization::NoteCommitment, Personalization::MerkleTree(31)] {
+            let inputs: Vec<Vec<bool>> = (0..5)
+                .map(|i| (0..(i * 150)).map(|_| rng.next_u32() % 2 != 0).collect())
+                .collect();
+
+            let hasher = PedersenHasher::<Bls12>::new(*personalization, params);
+            let hashes = hasher.hash_many(inputs.iter().cloned());
+
+            assert_eq!(hashes.len(), inputs.len());
+            for (input, hash) in inputs.into_iter().zip(hashes) {
+                assert_eq!(hasher.hash(input.clone()).into_xy(), hash);
+                assert_eq!(pedersen_hash::<Bls12, _>(*personalization, input, params).into_xy(), hash);
+            }
+        }
+    }
 }
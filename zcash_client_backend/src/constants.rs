@@ -1,4 +1,215 @@
 //! Zcash global and per-network constants.
 
+use bech32::Error;
+use pairing::bls12_381::Bls12;
+use sapling_crypto::primitives::PaymentAddress;
+use zcash_primitives::{
+    consensus::{UpgradeHeights, MAIN_NETWORK_UPGRADES, TEST_NETWORK_UPGRADES},
+    zip32::{ExtendedFullViewingKey, ExtendedSpendingKey},
+};
+
+use crate::{encoding, keys};
+
 pub mod mainnet;
+pub mod regtest;
 pub mod testnet;
+
+/// The set of per-network constants used by this crate.
+///
+/// The constants for the Zcash networks are available as [`MAIN_NETWORK`],
+/// [`TEST_NETWORK`], and [`REGTEST_NETWORK`]. Forks and private chains can construct
+/// their own, and use its methods to derive keys and encode them for that network.
+///
+/// # Examples
+///
+/// ```
+/// use zcash_client_backend::constants::NetworkConstants;
+/// use zcash_primitives::{consensus::UpgradeHeights, zip32::ExtendedFullViewingKey};
+///
+/// let network = NetworkConstants {
+///     coin_type: 1234,
+///     hrp_sapling_extended_spending_key: "secret-extended-key-mychain",
+///     hrp_sapling_extended_full_viewing_key: "zxviewmychain",
+///     hrp_sapling_payment_address: "zmychain",
+///     upgrades: UpgradeHeights {
+///         overwinter: Some(1),
+///         sapling: Some(1),
+///     },
+/// };
+///
+/// let extsk = network.spending_key(&[0; 32][..], 0);
+/// let (_, addr) = ExtendedFullViewingKey::from(&extsk).default_address().unwrap();
+/// let encoded = network.encode_payment_address(&addr);
+/// assert_eq!(network.decode_payment_address(&encoded), Ok(Some(addr)));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NetworkConstants<'a> {
+    /// The coin type used in ZIP 32 derivation paths.
+    pub coin_type: u32,
+    /// The HRP for a Bech32-encoded [`ExtendedSpendingKey`].
+    pub hrp_sapling_extended_spending_key: &'a str,
+    /// The HRP for a Bech32-encoded [`ExtendedFullViewingKey`].
+    pub hrp_sapling_extended_full_viewing_key: &'a str,
+    /// The HRP for a Bech32-encoded [`PaymentAddress`].
+    pub hrp_sapling_payment_address: &'a str,
+    /// The activation heights of the network upgrades.
+    pub upgrades: UpgradeHeights,
+}
+
+impl<'a> NetworkConstants<'a> {
+    /// Derives the ZIP 32 [`ExtendedSpendingKey`] for the given account on this network.
+    ///
+    /// See [`keys::spending_key`].
+    pub fn spending_key(&self, seed: &[u8], account: u32) -> ExtendedSpendingKey {
+        keys::spending_key(seed, self.coin_type, account)
+    }
+
+    /// Writes an [`ExtendedSpendingKey`] as a Bech32-encoded string for this network.
+    pub fn encode_extended_spending_key(&self, extsk: &ExtendedSpendingKey) -> String {
+        encoding::encode_extended_spending_key(self.hrp_sapling_extended_spending_key, extsk)
+    }
+
+    /// Decodes an [`ExtendedSpendingKey`] for this network from a Bech32-encoded string.
+    pub fn decode_extended_spending_key(
+        &self,
+        s: &str,
+    ) -> Result<Option<ExtendedSpendingKey>, Error> {
+        encoding::decode_extended_spending_key(self.hrp_sapling_extended_spending_key, s)
+    }
+
+    /// Writes an [`ExtendedFullViewingKey`] as a Bech32-encoded string for this network.
+    pub fn encode_extended_full_viewing_key(&self, extfvk: &ExtendedFullViewingKey) -> String {
+        encoding::encode_extended_full_viewing_key(
+            self.hrp_sapling_extended_full_viewing_key,
+            extfvk,
+        )
+    }
+
+    /// Decodes an [`ExtendedFullViewingKey`] for this network from a Bech32-encoded
+    /// string.
+    pub fn decode_extended_full_viewing_key(
+        &self,
+        s: &str,
+    ) -> Result<Option<ExtendedFullViewingKey>, Error> {
+        encoding::decode_extended_full_viewing_key(self.hrp_sapling_extended_full_viewing_key, s)
+    }
+
+    /// Writes a [`PaymentAddress`] as a Bech32-encoded string for this network.
+    pub fn encode_payment_address(&self, addr: &PaymentAddress<Bls12>) -> String {
+        encoding::encode_payment_address(self.hrp_sapling_payment_address, addr)
+    }
+
+    /// Decodes a [`PaymentAddress`] for this network from a Bech32-encoded string.
+    pub fn decode_payment_address(&self, s: &str) -> Result<Option<PaymentAddress<Bls12>>, Error> {
+        encoding::decode_payment_address(self.hrp_sapling_payment_address, s)
+    }
+}
+
+/// The constants for the Zcash mainnet.
+pub const MAIN_NETWORK: NetworkConstants<'static> = NetworkConstants {
+    coin_type: mainnet::COIN_TYPE,
+    hrp_sapling_extended_spending_key: mainnet::HRP_SAPLING_EXTENDED_SPENDING_KEY,
+    hrp_sapling_extended_full_viewing_key: mainnet::HRP_SAPLING_EXTENDED_FULL_VIEWING_KEY,
+    hrp_sapling_payment_address: mainnet::HRP_SAPLING_PAYMENT_ADDRESS,
+    upgrades: MAIN_NETWORK_UPGRADES,
+};
+
+/// The constants for the Zcash testnet.
+pub const TEST_NETWORK: NetworkConstants<'static> = NetworkConstants {
+    coin_type: testnet::COIN_TYPE,
+    hrp_sapling_extended_spending_key: testnet::HRP_SAPLING_EXTENDED_SPENDING_KEY,
+    hrp_sapling_extended_full_viewing_key: testnet::HRP_SAPLING_EXTENDED_FULL_VIEWING_KEY,
+    hrp_sapling_payment_address: testnet::HRP_SAPLING_PAYMENT_ADDRESS,
+    upgrades: TEST_NETWORK_UPGRADES,
+};
+
+/// The constants for a Zcash regtest network.
+///
+/// No network upgrades are active by default on regtest.
+pub const REGTEST_NETWORK: NetworkConstants<'static> = NetworkConstants {
+    coin_type: regtest::COIN_TYPE,
+    hrp_sapling_extended_spending_key: regtest::HRP_SAPLING_EXTENDED_SPENDING_KEY,
+    hrp_sapling_extended_full_viewing_key: regtest::HRP_SAPLING_EXTENDED_FULL_VIEWING_KEY,
+    hrp_sapling_payment_address: regtest::HRP_SAPLING_PAYMENT_ADDRESS,
+    upgrades: UpgradeHeights {
+        overwinter: None,
+        sapling: None,
+    },
+};
+
+#[cfg(test)]
+mod tests {
+    use zcash_primitives::{
+        consensus::{NetworkUpgrade, UpgradeHeights},
+        zip32::ExtendedFullViewingKey,
+    };
+
+    use super::{testnet, NetworkConstants, MAIN_NETWORK, REGTEST_NETWORK, TEST_NETWORK};
+    use crate::{encoding, keys};
+
+    #[test]
+    fn network_encodings() {
+        let network = NetworkConstants {
+            coin_type: 1234,
+            hrp_sapling_extended_spending_key: "secret-extended-key-mychain",
+            hrp_sapling_extended_full_viewing_key: "zxviewmychain",
+            hrp_sapling_payment_address: "zmychain",
+            upgrades: UpgradeHeights {
+                overwinter: Some(1),
+                sapling: Some(1),
+            },
+        };
+
+        let extsk = network.spending_key(&[0; 32][..], 0);
+        assert!(extsk == keys::spending_key(&[0; 32][..], 1234, 0));
+        let extfvk = ExtendedFullViewingKey::from(&extsk);
+        let (_, addr) = extfvk.default_address().unwrap();
+
+        let encoded = network.encode_extended_spending_key(&extsk);
+        assert!(encoded.starts_with("secret-extended-key-mychain1"));
+        assert!(network.decode_extended_spending_key(&encoded).unwrap() == Some(extsk.clone()));
+        assert!(TEST_NETWORK
+            .decode_extended_spending_key(&encoded)
+            .unwrap()
+            .is_none());
+
+        let encoded = network.encode_extended_full_viewing_key(&extfvk);
+        assert!(encoded.starts_with("zxviewmychain1"));
+        assert!(network.decode_extended_full_viewing_key(&encoded).unwrap() == Some(extfvk));
+        assert!(TEST_NETWORK
+            .decode_extended_full_viewing_key(&encoded)
+            .unwrap()
+            .is_none());
+
+        let encoded = network.encode_payment_address(&addr);
+        assert!(encoded.starts_with("zmychain1"));
+        assert_eq!(
+            network.decode_payment_address(&encoded),
+            Ok(Some(addr.clone()))
+        );
+        assert_eq!(TEST_NETWORK.decode_payment_address(&encoded), Ok(None));
+
+        // The Zcash networks match the existing per-network constants.
+        let extsk = TEST_NETWORK.spending_key(&[0; 32][..], 0);
+        assert_eq!(
+            TEST_NETWORK.encode_extended_spending_key(&extsk),
+            encoding::encode_extended_spending_key(
+                testnet::HRP_SAPLING_EXTENDED_SPENDING_KEY,
+                &keys::spending_key(&[0; 32][..], testnet::COIN_TYPE, 0)
+            )
+        );
+    }
+
+    #[test]
+    fn network_upgrades() {
+        assert!(MAIN_NETWORK
+            .upgrades
+            .is_active(NetworkUpgrade::Sapling, 419_200));
+        assert!(!TEST_NETWORK
+            .upgrades
+            .is_active(NetworkUpgrade::Sapling, 279_999));
+        assert!(!REGTEST_NETWORK
+            .upgrades
+            .is_active(NetworkUpgrade::Overwinter, 500_000_000));
+    }
+}
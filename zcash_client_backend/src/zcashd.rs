@@ -0,0 +1,246 @@
+//! Support for migrating key material from zcashd wallets.
+
+use std::error;
+use std::fmt;
+use zcash_primitives::zip32::ExtendedSpendingKey;
+
+use crate::encoding::decode_extended_spending_key;
+
+const BEST_BLOCK_PREFIX: &str = "# * Best block at time of backup was ";
+
+/// Errors that can occur while parsing the output of `z_exportwallet`.
+///
+/// Line numbers are 1-indexed.
+#[derive(Debug, PartialEq)]
+pub enum DumpError {
+    /// A Sapling key line was missing its creation time.
+    MissingTime(usize),
+    /// A Sapling extended spending key could not be decoded.
+    InvalidSpendingKey(usize),
+    /// A Sapling key's `hdkeypath` could not be parsed.
+    InvalidKeyPath(usize),
+}
+
+impl fmt::Display for DumpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DumpError::MissingTime(line) => write!(f, "Missing key creation time on line {}", line),
+            DumpError::InvalidSpendingKey(line) => {
+                write!(f, "Invalid Sapling spending key on line {}", line)
+            }
+            DumpError::InvalidKeyPath(line) => write!(f, "Invalid hdkeypath on line {}", line),
+        }
+    }
+}
+
+impl error::Error for DumpError {}
+
+/// A Sapling spending key exported from a zcashd wallet.
+pub struct SaplingKey {
+    pub extsk: ExtendedSpendingKey,
+    /// The time at which zcashd created or imported the key, in ISO 8601 format.
+    pub created: String,
+    /// The ZIP 32 account index of the key, if it was derived from the wallet's HD
+    /// seed. Keys added with `z_importkey` do not have an account index.
+    pub account: Option<u32>,
+}
+
+/// The Sapling key material in the output of zcashd's `z_exportwallet` RPC method.
+pub struct WalletDump {
+    /// The height of the chain tip when the dump was created, if recorded.
+    ///
+    /// This is an upper bound on the height of the first transaction received by any
+    /// of the keys that existed at the time, and so it is not a wallet birthday.
+    pub best_block_height: Option<u32>,
+    pub sapling_keys: Vec<SaplingKey>,
+}
+
+/// Parses an `hdkeypath` of the form `m/32'/coin_type'/account'`, returning the
+/// account index.
+fn parse_account(path: &str, coin_type: u32) -> Option<Option<u32>> {
+    let indices = path
+        .split('/')
+        .skip(1)
+        .map(|i| {
+            if i.ends_with('\'') {
+                i[..i.len() - 1].parse::<u32>().ok()
+            } else {
+                None
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    if !path.starts_with("m/") {
+        None
+    } else if indices.len() == 3 && indices[0] == 32 && indices[1] == coin_type {
+        Some(Some(indices[2]))
+    } else {
+        // A valid path, but not for a ZIP 32 account on this network
+        Some(None)
+    }
+}
+
+/// Parses the Sapling key material from the output of zcashd's `z_exportwallet` RPC
+/// method.
+///
+/// Only Sapling spending keys for the network given by `hrp` and `coin_type` are
+/// returned. Transparent and Sprout keys are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use zcash_client_backend::{
+///     constants::testnet::{COIN_TYPE, HRP_SAPLING_EXTENDED_SPENDING_KEY},
+///     zcashd::parse_wallet_dump,
+/// };
+///
+/// let dump = "# Wallet dump created by Zcash v2.0.5\n# End of dump\n";
+/// let parsed = parse_wallet_dump(HRP_SAPLING_EXTENDED_SPENDING_KEY, COIN_TYPE, dump).unwrap();
+/// assert!(parsed.sapling_keys.is_empty());
+/// ```
+pub fn parse_wallet_dump(hrp: &str, coin_type: u32, dump: &str) -> Result<WalletDump, DumpError> {
+    let key_prefix = format!("{}1", hrp);
+    let mut best_block_height = None;
+    let mut sapling_keys = vec![];
+
+    for (i, line) in dump.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+
+        if line.starts_with(BEST_BLOCK_PREFIX) {
+            best_block_height = line[BEST_BLOCK_PREFIX.len()..]
+                .split(' ')
+                .next()
+                .and_then(|height| height.parse().ok());
+            continue;
+        }
+
+        if !line.starts_with(&key_prefix) {
+            continue;
+        }
+
+        let mut parts = line.splitn(2, '#');
+        let mut fields = parts.next().unwrap_or("").split_whitespace();
+        let comment = parts.next().unwrap_or("");
+
+        let extsk = fields
+            .next()
+            .and_then(|key| decode_extended_spending_key(hrp, key).ok())
+            .and_then(|extsk| extsk)
+            .ok_or(DumpError::InvalidSpendingKey(line_number))?;
+        let created = fields
+            .next()
+            .ok_or(DumpError::MissingTime(line_number))?
+            .to_owned();
+
+        let account = match comment
+            .split_whitespace()
+            .find(|field| field.starts_with("hdkeypath="))
+        {
+            Some(field) => parse_account(&field["hdkeypath=".len()..], coin_type)
+                .ok_or(DumpError::InvalidKeyPath(line_number))?,
+            None => None,
+        };
+
+        sapling_keys.push(SaplingKey {
+            extsk,
+            created,
+            account,
+        });
+    }
+
+    Ok(WalletDump {
+        best_block_height,
+        sapling_keys,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use zcash_primitives::zip32::ExtendedSpendingKey;
+
+    use super::{parse_wallet_dump, DumpError};
+    use crate::{
+        constants::testnet::{COIN_TYPE, HRP_SAPLING_EXTENDED_SPENDING_KEY},
+        encoding::encode_extended_spending_key,
+        keys::spending_key,
+    };
+
+    fn encode(extsk: &ExtendedSpendingKey) -> String {
+        encode_extended_spending_key(HRP_SAPLING_EXTENDED_SPENDING_KEY, extsk)
+    }
+
+    #[test]
+    fn wallet_dump() {
+        let hd_key = spending_key(&[0; 32], COIN_TYPE, 3);
+        let imported_key = ExtendedSpendingKey::master(&[1; 32]);
+
+        let dump = format!(
+            "# Wallet dump created by Zcash v2.0.5-2 (2019-05-15 16:25:40 -0600)
+# * Created on 2019-06-01T00:00:00Z
+# * Best block at time of backup was 512345 (0000000000000000000000000000000000000000000000000000000000000000),
+#   mined on 2019-05-31T23:59:00Z
+
+cVqmcT2qBQbp8xrXvVFNDBJ1kUxqFDyDgyRPCjXrzLxhQN8iTAM3 2019-05-15T00:00:00Z change=1 # addr=tmAbCdEf
+# Zkeys
+
+# Sapling keys
+
+{} 2019-05-16T00:00:00Z # zaddr=ztestsapling1abc hdkeypath=m/32'/1'/3' seedFpr=0000
+{} 2019-05-17T00:00:00Z # zaddr=ztestsapling1def
+
+# End of dump
+",
+            encode(&hd_key),
+            encode(&imported_key),
+        );
+
+        let parsed =
+            parse_wallet_dump(HRP_SAPLING_EXTENDED_SPENDING_KEY, COIN_TYPE, &dump).unwrap();
+        assert_eq!(parsed.best_block_height, Some(512345));
+        assert_eq!(parsed.sapling_keys.len(), 2);
+
+        assert!(parsed.sapling_keys[0].extsk == hd_key);
+        assert_eq!(parsed.sapling_keys[0].created, "2019-05-16T00:00:00Z");
+        assert_eq!(parsed.sapling_keys[0].account, Some(3));
+
+        assert!(parsed.sapling_keys[1].extsk == imported_key);
+        assert_eq!(parsed.sapling_keys[1].created, "2019-05-17T00:00:00Z");
+        assert_eq!(parsed.sapling_keys[1].account, None);
+    }
+
+    #[test]
+    fn wallet_dump_errors() {
+        let key = encode(&spending_key(&[0; 32], COIN_TYPE, 0));
+
+        let mut corrupted = key.clone();
+        corrupted.pop();
+        assert_eq!(
+            parse_wallet_dump(
+                HRP_SAPLING_EXTENDED_SPENDING_KEY,
+                COIN_TYPE,
+                &format!(
+                    "\n{} 2019-05-16T00:00:00Z # zaddr=ztestsapling1abc",
+                    corrupted
+                ),
+            )
+            .err(),
+            Some(DumpError::InvalidSpendingKey(2))
+        );
+
+        assert_eq!(
+            parse_wallet_dump(HRP_SAPLING_EXTENDED_SPENDING_KEY, COIN_TYPE, &key).err(),
+            Some(DumpError::MissingTime(1))
+        );
+
+        assert_eq!(
+            parse_wallet_dump(
+                HRP_SAPLING_EXTENDED_SPENDING_KEY,
+                COIN_TYPE,
+                &format!("{} 2019-05-16T00:00:00Z # hdkeypath=m/32'/1/0'", key),
+            )
+            .err(),
+            Some(DumpError::InvalidKeyPath(1))
+        );
+    }
+}
@@ -0,0 +1,62 @@
+#![feature(test)]
+
+extern crate pairing;
+extern crate test;
+extern crate zcash_primitives;
+
+use pairing::bls12_381::FrRepr;
+use zcash_primitives::merkle_tree::{CommitmentTree, IncrementalWitness};
+use zcash_primitives::sapling::Node;
+
+const WITNESSES: u64 = 20;
+const BATCH_SIZE: u64 = 32;
+
+fn setup() -> (
+    CommitmentTree<Node>,
+    Vec<IncrementalWitness<Node>>,
+    Vec<Node>,
+) {
+    let node = |i: u64| Node::new(FrRepr([i, 0, 0, 0]));
+
+    let mut tree = CommitmentTree::new();
+    let mut witnesses: Vec<IncrementalWitness<Node>> = vec![];
+    for i in 0..WITNESSES {
+        for witness in witnesses.iter_mut() {
+            witness.append(node(i)).unwrap();
+        }
+        tree.append(node(i)).unwrap();
+        witnesses.push(IncrementalWitness::from_tree(&tree));
+    }
+
+    let nodes = (WITNESSES..WITNESSES + BATCH_SIZE).map(node).collect();
+    (tree, witnesses, nodes)
+}
+
+#[bench]
+fn bench_witnesses_append(b: &mut test::Bencher) {
+    let (tree, witnesses, nodes) = setup();
+
+    b.iter(|| {
+        let mut tree = tree.clone();
+        let mut witnesses = witnesses.clone();
+        for node in &nodes {
+            tree.append(*node).unwrap();
+            for witness in witnesses.iter_mut() {
+                witness.append(*node).unwrap();
+            }
+        }
+        (tree, witnesses)
+    });
+}
+
+#[bench]
+fn bench_witnesses_append_batch(b: &mut test::Bencher) {
+    let (tree, witnesses, nodes) = setup();
+
+    b.iter(|| {
+        let mut tree = tree.clone();
+        let mut witnesses = witnesses.clone();
+        IncrementalWitness::append_batch(&mut tree, witnesses.iter_mut(), &nodes).unwrap();
+        (tree, witnesses)
+    });
+}
@@ -0,0 +1,41 @@
+//! Prints a summary of a hex-encoded transaction.
+//!
+//! Usage: `cargo run --example decode_tx -- <hex>`, or pass the hex on stdin.
+
+extern crate hex;
+extern crate zcash_primitives;
+
+use std::env;
+use std::io::{self, Read};
+use std::process;
+
+use zcash_primitives::transaction::Transaction;
+
+fn main() {
+    let tx_hex = match env::args().nth(1) {
+        Some(tx_hex) => tx_hex,
+        None => {
+            let mut tx_hex = String::new();
+            io::stdin()
+                .read_to_string(&mut tx_hex)
+                .expect("could not read from stdin");
+            tx_hex
+        }
+    };
+
+    let tx_bytes = match hex::decode(tx_hex.trim()) {
+        Ok(tx_bytes) => tx_bytes,
+        Err(e) => {
+            eprintln!("Invalid hex: {}", e);
+            process::exit(1);
+        }
+    };
+
+    match Transaction::read(&tx_bytes[..]) {
+        Ok(tx) => println!("{}", tx.describe()),
+        Err(e) => {
+            eprintln!("Invalid transaction: {}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Proofs that the prover can view the notes received by a Sapling payment address.
+//!
+//! A proof is a Schnorr signature over a caller-chosen message, using the address's
+//! diversified base `g_d` as the generator and its transmission key `pk_d = [ivk] g_d`
+//! as the public key. It demonstrates knowledge of the incoming viewing key, and so
+//! confers no spend authority. Services should include a fresh challenge in the
+//! message to prevent replays.
+
+use blake2b_simd::Params as Blake2bParams;
+use ff::{Field, PrimeField, PrimeFieldRepr};
+use pairing::bls12_381::Bls12;
+use rand_core::RngCore;
+use sapling_crypto::{
+    jubjub::{
+        edwards,
+        fs::{Fs, FsRepr},
+        ToUniform, Unknown,
+    },
+    primitives::PaymentAddress,
+};
+use std::io::{self, Read, Write};
+
+use crate::{constants::IVK_PROOF_PERSONALIZATION, JUBJUB};
+
+/// A proof of knowledge of the incoming viewing key for a payment address.
+pub struct AddressProof {
+    r: edwards::Point<Bls12, Unknown>,
+    s: Fs,
+}
+
+fn challenge(addr: &PaymentAddress<Bls12>, r: &edwards::Point<Bls12, Unknown>, msg: &[u8]) -> Fs {
+    let mut h = Blake2bParams::new()
+        .hash_length(64)
+        .personal(IVK_PROOF_PERSONALIZATION)
+        .to_state();
+    h.update(&addr.diversifier.0);
+    let mut buf = [0u8; 32];
+    addr.pk_d.write(&mut buf[..]).unwrap();
+    h.update(&buf);
+    r.write(&mut buf[..]).unwrap();
+    h.update(&buf);
+    h.update(msg);
+    Fs::to_uniform(h.finalize().as_bytes())
+}
+
+impl AddressProof {
+    /// Creates a proof over `msg` that the holder of `ivk` can view the notes received
+    /// by `addr`.
+    ///
+    /// Returns `None` if `addr` was not derived from `ivk`.
+    pub fn create<R: RngCore>(
+        ivk: &Fs,
+        addr: &PaymentAddress<Bls12>,
+        msg: &[u8],
+        rng: &mut R,
+    ) -> Option<Self> {
+        let g_d = addr.g_d(&JUBJUB)?;
+        if g_d.mul(*ivk, &JUBJUB) != addr.pk_d {
+            return None;
+        }
+
+        let mut buffer = [0u8; 64];
+        rng.fill_bytes(&mut buffer);
+        let nonce = Fs::to_uniform(&buffer[..]);
+
+        let r = g_d.mul(nonce, &JUBJUB).into();
+        let mut s = challenge(addr, &r, msg);
+        s.mul_assign(ivk);
+        s.add_assign(&nonce);
+
+        Some(AddressProof { r, s })
+    }
+
+    /// Verifies that this is a valid proof over `msg` for `addr`.
+    pub fn verify(&self, addr: &PaymentAddress<Bls12>, msg: &[u8]) -> bool {
+        let g_d = match addr.g_d(&JUBJUB) {
+            Some(g_d) => g_d,
+            None => return false,
+        };
+        let c = challenge(addr, &self.r, msg);
+
+        // [s] g_d == R + [c] pk_d
+        let lhs: edwards::Point<Bls12, Unknown> = g_d.mul(self.s, &JUBJUB).into();
+        let rhs: edwards::Point<Bls12, Unknown> = addr.pk_d.mul(c, &JUBJUB).into();
+        lhs == rhs.add(&self.r, &JUBJUB)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let r = edwards::Point::<Bls12, Unknown>::read(&mut reader, &JUBJUB)?;
+        let mut repr = FsRepr::default();
+        repr.read_le(&mut reader)?;
+        let s = Fs::from_repr(repr).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(AddressProof { r, s })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.r.write(&mut writer)?;
+        self.s.into_repr().write_le(&mut writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_os::OsRng;
+
+    use super::AddressProof;
+    use crate::zip32::{ExtendedFullViewingKey, ExtendedSpendingKey};
+
+    #[test]
+    fn create_and_verify() {
+        let mut rng = OsRng;
+
+        let xfvk = ExtendedFullViewingKey::from(&ExtendedSpendingKey::master(&[0; 32]));
+        let other = ExtendedFullViewingKey::from(&ExtendedSpendingKey::master(&[1; 32]));
+        let ivk = xfvk.fvk.vk.ivk();
+        let (_, addr) = xfvk.default_address().unwrap();
+        let (_, other_addr) = other.default_address().unwrap();
+
+        let proof = AddressProof::create(&ivk, &addr, b"challenge", &mut rng).unwrap();
+        assert!(proof.verify(&addr, b"challenge"));
+        assert!(!proof.verify(&addr, b"another challenge"));
+        assert!(!proof.verify(&other_addr, b"challenge"));
+
+        let mut encoded = vec![];
+        proof.write(&mut encoded).unwrap();
+        assert_eq!(encoded.len(), 64);
+        let decoded = AddressProof::read(&encoded[..]).unwrap();
+        assert!(decoded.verify(&addr, b"challenge"));
+
+        // An ivk can't be used to prove ownership of another key's address
+        assert!(AddressProof::create(&ivk, &other_addr, b"challenge", &mut rng).is_none());
+    }
+}
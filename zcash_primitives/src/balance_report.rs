@@ -0,0 +1,406 @@
+//! Reports of the balance held by a viewing key, for auditing.
+//!
+//! A [`BalanceReport`] reveals the contents of a set of notes, and the paths from their
+//! commitments to the root of the note commitment tree at a given height. An auditor
+//! that is given the full viewing key can check that it received every note, check the
+//! report against the published tree root without any access to the wallet, and derive
+//! the notes' nullifiers to check that none of them have been revealed on chain.
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use ff::{PrimeField, PrimeFieldRepr};
+use pairing::bls12_381::Bls12;
+use sapling_crypto::{
+    jubjub::{
+        edwards,
+        fs::{Fs, FsRepr},
+    },
+    primitives::{Diversifier, PaymentAddress},
+};
+use std::collections::HashSet;
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::{
+    merkle_tree::{CommitmentTreeWitness, Hashable},
+    sapling::{compute_note_commitment, compute_nullifier, Node, SAPLING_COMMITMENT_TREE_DEPTH},
+    serialize::Vector,
+    transaction::components::{Amount, MAX_MONEY},
+    zip32::ExtendedFullViewingKey,
+    JUBJUB,
+};
+
+// depth || (32 || sibling) * depth || position
+const WITNESS_SIZE: usize = 1 + 33 * SAPLING_COMMITMENT_TREE_DEPTH + 8;
+
+/// Errors that can occur while checking a [`BalanceReport`].
+#[derive(Debug, PartialEq)]
+pub enum ReportError {
+    /// The note with the given index has an invalid recipient address, or the address
+    /// was not derived from the given viewing key.
+    InvalidNote(usize),
+    /// The commitment of the note with the given index is not in the tree with the
+    /// report's anchor.
+    AnchorMismatch(usize),
+    /// The note with the given index is at the same position in the tree as an earlier
+    /// note.
+    DuplicateNote(usize),
+    /// The total value of the notes is out of range.
+    ValueOutOfRange,
+    /// The total value of the notes is not the balance claimed by the report.
+    BalanceMismatch(Amount),
+    /// The given viewing key is not the one the report was created for.
+    ViewingKeyMismatch,
+}
+
+impl fmt::Display for ReportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReportError::InvalidNote(i) => write!(f, "Invalid recipient for note {}", i),
+            ReportError::AnchorMismatch(i) => {
+                write!(f, "Note {} is not in the tree with the reported anchor", i)
+            }
+            ReportError::DuplicateNote(i) => write!(f, "Note {} is reported twice", i),
+            ReportError::ValueOutOfRange => write!(f, "Total value out of range"),
+            ReportError::BalanceMismatch(total) => {
+                write!(
+                    f,
+                    "Notes total {} zatoshis, not the claimed balance",
+                    total.0
+                )
+            }
+            ReportError::ViewingKeyMismatch => {
+                write!(f, "Report was not created for this viewing key")
+            }
+        }
+    }
+}
+
+impl error::Error for ReportError {}
+
+/// A note included in a [`BalanceReport`].
+pub struct ReportedNote {
+    pub to: PaymentAddress<Bls12>,
+    pub value: u64,
+    pub rcm: Fs,
+    pub witness: CommitmentTreeWitness<Node>,
+}
+
+impl ReportedNote {
+    fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut diversifier = Diversifier([0; 11]);
+        reader.read_exact(&mut diversifier.0)?;
+        let pk_d = edwards::Point::<Bls12, _>::read(&mut reader, &JUBJUB)?
+            .as_prime_order(&JUBJUB)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "pk_d not in prime order subgroup",
+                )
+            })?;
+
+        let value = reader.read_u64::<LittleEndian>()?;
+
+        let mut rcm = FsRepr::default();
+        rcm.read_le(&mut reader)?;
+        let rcm = Fs::from_repr(rcm).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let mut witness = vec![0; WITNESS_SIZE];
+        reader.read_exact(&mut witness)?;
+        let witness = CommitmentTreeWitness::from_slice(&witness)
+            .map_err(|()| io::Error::new(io::ErrorKind::InvalidData, "invalid witness"))?;
+
+        Ok(ReportedNote {
+            to: PaymentAddress { pk_d, diversifier },
+            value,
+            rcm,
+            witness,
+        })
+    }
+
+    fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.to.diversifier.0)?;
+        self.to.pk_d.write(&mut writer)?;
+        writer.write_u64::<LittleEndian>(self.value)?;
+        self.rcm.into_repr().write_le(&mut writer)?;
+        self.witness.write(&mut writer)
+    }
+}
+
+/// The notes held by a viewing key at a given height.
+pub struct BalanceReport {
+    /// The ZIP 32 fingerprint of the full viewing key that received the notes.
+    pub fvk_fingerprint: [u8; 32],
+    pub height: u32,
+    /// The root of the Sapling note commitment tree at `height`.
+    pub anchor: Node,
+    /// The balance that the wallet claims to hold at `height`.
+    pub balance: Amount,
+    pub notes: Vec<ReportedNote>,
+}
+
+impl BalanceReport {
+    pub fn new(
+        extfvk: &ExtendedFullViewingKey,
+        height: u32,
+        anchor: Node,
+        balance: Amount,
+        notes: Vec<ReportedNote>,
+    ) -> Self {
+        BalanceReport {
+            fvk_fingerprint: extfvk.fingerprint(),
+            height,
+            anchor,
+            balance,
+            notes,
+        }
+    }
+
+    /// Checks that every reported note was received by `extfvk` and is in the tree with
+    /// the report's anchor, and that their total value is the claimed balance.
+    ///
+    /// The caller must separately check that the anchor is the root of the Sapling
+    /// note commitment tree at the report's height.
+    pub fn verify(&self, extfvk: &ExtendedFullViewingKey) -> Result<Amount, ReportError> {
+        if extfvk.fingerprint() != self.fvk_fingerprint {
+            return Err(ReportError::ViewingKeyMismatch);
+        }
+
+        let mut positions = HashSet::new();
+        let mut total = 0u64;
+
+        for (i, note) in self.notes.iter().enumerate() {
+            if extfvk.address_index(&note.to).is_none() {
+                return Err(ReportError::InvalidNote(i));
+            }
+            let cm = compute_note_commitment(&note.to, note.value, note.rcm, &JUBJUB)
+                .ok_or(ReportError::InvalidNote(i))?;
+            // Only the low bits of the position are used by the path, so a note could
+            // otherwise be reported again at a position outside the tree.
+            if note.witness.position >> SAPLING_COMMITMENT_TREE_DEPTH != 0
+                || note.witness.root(Node::new(cm.into_repr())) != Some(self.anchor)
+            {
+                return Err(ReportError::AnchorMismatch(i));
+            }
+            if !positions.insert(note.witness.position) {
+                return Err(ReportError::DuplicateNote(i));
+            }
+
+            total = total
+                .checked_add(note.value)
+                .filter(|total| *total <= MAX_MONEY as u64)
+                .ok_or(ReportError::ValueOutOfRange)?;
+        }
+
+        let total = Amount(total as i64);
+        if total == self.balance {
+            Ok(total)
+        } else {
+            Err(ReportError::BalanceMismatch(total))
+        }
+    }
+
+    /// Returns the nullifiers of the reported notes, which an auditor can check have
+    /// not been revealed on chain.
+    ///
+    /// Returns an error if `extfvk` is not the key the report was created for, or did
+    /// not receive one of the notes.
+    pub fn nullifiers(
+        &self,
+        extfvk: &ExtendedFullViewingKey,
+    ) -> Result<Vec<[u8; 32]>, ReportError> {
+        if extfvk.fingerprint() != self.fvk_fingerprint {
+            return Err(ReportError::ViewingKeyMismatch);
+        }
+
+        self.notes
+            .iter()
+            .enumerate()
+            .map(|(i, note)| {
+                extfvk
+                    .address_index(&note.to)
+                    .and_then(|_| {
+                        compute_nullifier(
+                            &extfvk.fvk,
+                            &note.to,
+                            note.value,
+                            note.rcm,
+                            note.witness.position,
+                            &JUBJUB,
+                        )
+                    })
+                    .ok_or(ReportError::InvalidNote(i))
+            })
+            .collect()
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut fvk_fingerprint = [0; 32];
+        reader.read_exact(&mut fvk_fingerprint)?;
+        let height = reader.read_u32::<LittleEndian>()?;
+        let anchor = Node::read(&mut reader)?;
+        let balance = Amount::read_i64(&mut reader, false)?;
+        let notes = Vector::read(&mut reader, |r| ReportedNote::read(r))?;
+
+        Ok(BalanceReport {
+            fvk_fingerprint,
+            height,
+            anchor,
+            balance,
+            notes,
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.fvk_fingerprint)?;
+        writer.write_u32::<LittleEndian>(self.height)?;
+        self.anchor.write(&mut writer)?;
+        writer.write_i64::<LittleEndian>(self.balance.0)?;
+        Vector::write(&mut writer, &self.notes, |w, note| note.write(w))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::PrimeField;
+    use pairing::bls12_381::Bls12;
+    use sapling_crypto::{
+        jubjub::fs::{Fs, FsRepr},
+        primitives::PaymentAddress,
+    };
+
+    use super::{BalanceReport, ReportError, ReportedNote};
+    use crate::{
+        merkle_tree::{CommitmentTree, CommitmentTreeWitness, IncrementalWitness},
+        sapling::{compute_note_commitment, Node},
+        transaction::components::Amount,
+        zip32::{ExtendedFullViewingKey, ExtendedSpendingKey},
+        JUBJUB,
+    };
+
+    fn leaf(note: &ReportedNote) -> Node {
+        let cm = compute_note_commitment(&note.to, note.value, note.rcm, &JUBJUB).unwrap();
+        Node::new(cm.into_repr())
+    }
+
+    /// Appends the given notes to a new tree, and returns a report of them.
+    fn report(
+        xfvk: &ExtendedFullViewingKey,
+        notes: &[(PaymentAddress<Bls12>, u64)],
+        balance: i64,
+    ) -> BalanceReport {
+        let mut tree = CommitmentTree::new();
+        let mut witnesses: Vec<IncrementalWitness<Node>> = vec![];
+        let mut rcms = vec![];
+        for (i, (to, value)) in notes.iter().enumerate() {
+            let mut rcm_repr = FsRepr::default();
+            rcm_repr.as_mut()[0] = i as u64 + 1;
+            let rcm = Fs::from_repr(rcm_repr).unwrap();
+            let cm = compute_note_commitment(to, *value, rcm, &JUBJUB).unwrap();
+            let node = Node::new(cm.into_repr());
+
+            for witness in witnesses.iter_mut() {
+                witness.append(node).unwrap();
+            }
+            tree.append(node).unwrap();
+            witnesses.push(IncrementalWitness::from_tree(&tree));
+            rcms.push(rcm);
+        }
+
+        BalanceReport::new(
+            xfvk,
+            1000,
+            tree.root(),
+            Amount(balance),
+            notes
+                .iter()
+                .zip(rcms)
+                .zip(witnesses.iter())
+                .map(|(((to, value), rcm), witness)| ReportedNote {
+                    to: to.clone(),
+                    value: *value,
+                    rcm,
+                    witness: witness.path().unwrap(),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn balance_report() {
+        let xfvk = ExtendedFullViewingKey::from(&ExtendedSpendingKey::master(&[0; 32]));
+        let other = ExtendedFullViewingKey::from(&ExtendedSpendingKey::master(&[1; 32]));
+        let (_, to) = xfvk.default_address().unwrap();
+
+        let report = report(&xfvk, &[(to.clone(), 1000), (to, 2500)], 3500);
+        assert_eq!(report.verify(&xfvk), Ok(Amount(3500)));
+        assert_eq!(report.verify(&other), Err(ReportError::ViewingKeyMismatch));
+        assert_eq!(report.nullifiers(&xfvk).unwrap().len(), 2);
+        assert_eq!(
+            report.nullifiers(&other),
+            Err(ReportError::ViewingKeyMismatch)
+        );
+
+        let mut encoded = vec![];
+        report.write(&mut encoded).unwrap();
+        let mut decoded = BalanceReport::read(&encoded[..]).unwrap();
+        assert_eq!(decoded.verify(&xfvk), Ok(Amount(3500)));
+
+        // A note with a different value is not in the tree
+        decoded.notes[1].value = 2501;
+        assert_eq!(decoded.verify(&xfvk), Err(ReportError::AnchorMismatch(1)));
+    }
+
+    #[test]
+    fn note_sent_to_other_key() {
+        let xfvk = ExtendedFullViewingKey::from(&ExtendedSpendingKey::master(&[0; 32]));
+        let other = ExtendedFullViewingKey::from(&ExtendedSpendingKey::master(&[1; 32]));
+        let (_, to) = xfvk.default_address().unwrap();
+        let (_, other_to) = other.default_address().unwrap();
+
+        // The wallet knows the contents of a note it sent to another key, and that note
+        // is in the tree, but it must not count towards the wallet's balance.
+        let report = report(&xfvk, &[(to, 1000), (other_to, 2500)], 3500);
+        assert_eq!(report.verify(&xfvk), Err(ReportError::InvalidNote(1)));
+        assert_eq!(report.nullifiers(&xfvk), Err(ReportError::InvalidNote(1)));
+    }
+
+    #[test]
+    fn claimed_balance() {
+        let xfvk = ExtendedFullViewingKey::from(&ExtendedSpendingKey::master(&[0; 32]));
+        let (_, to) = xfvk.default_address().unwrap();
+
+        let report = report(&xfvk, &[(to.clone(), 1000), (to, 2500)], 4000);
+        assert_eq!(
+            report.verify(&xfvk),
+            Err(ReportError::BalanceMismatch(Amount(3500)))
+        );
+    }
+
+    #[test]
+    fn note_outside_tree() {
+        let xfvk = ExtendedFullViewingKey::from(&ExtendedSpendingKey::master(&[0; 32]));
+        let (_, to) = xfvk.default_address().unwrap();
+
+        let mut report = report(&xfvk, &[(to, 1000)], 2000);
+
+        // The same note at a position whose low bits give the same path
+        let copy = ReportedNote {
+            to: report.notes[0].to.clone(),
+            value: report.notes[0].value,
+            rcm: report.notes[0].rcm,
+            witness: CommitmentTreeWitness {
+                auth_path: report.notes[0].witness.auth_path.clone(),
+                position: report.notes[0].witness.position + (1 << 32),
+            },
+        };
+        assert_eq!(copy.witness.root(leaf(&copy)), Some(report.anchor));
+        report.notes.push(copy);
+        assert_eq!(report.verify(&xfvk), Err(ReportError::AnchorMismatch(1)));
+
+        // Such a report cannot be read
+        let mut encoded = vec![];
+        report.write(&mut encoded).unwrap();
+        assert!(BalanceReport::read(&encoded[..]).is_err());
+    }
+}
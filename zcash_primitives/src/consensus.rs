@@ -0,0 +1,64 @@
+//! Consensus parameters.
+
+/// A network upgrade that changes the transaction format.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NetworkUpgrade {
+    /// [Overwinter](https://z.cash/upgrade/overwinter/).
+    Overwinter,
+    /// [Sapling](https://z.cash/upgrade/sapling/).
+    Sapling,
+}
+
+/// The activation heights of the network upgrades on a network.
+///
+/// An upgrade with a height of `None` is never active, as is the default for a regtest
+/// network.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UpgradeHeights {
+    pub overwinter: Option<u32>,
+    pub sapling: Option<u32>,
+}
+
+impl UpgradeHeights {
+    /// Returns the activation height of the given network upgrade, if any.
+    pub fn activation_height(&self, nu: NetworkUpgrade) -> Option<u32> {
+        match nu {
+            NetworkUpgrade::Overwinter => self.overwinter,
+            NetworkUpgrade::Sapling => self.sapling,
+        }
+    }
+
+    /// Returns whether the given network upgrade is active at the given height.
+    pub fn is_active(&self, nu: NetworkUpgrade, height: u32) -> bool {
+        match self.activation_height(nu) {
+            Some(activation) => height >= activation,
+            None => false,
+        }
+    }
+}
+
+/// The network upgrade activation heights for the Zcash mainnet.
+pub const MAIN_NETWORK_UPGRADES: UpgradeHeights = UpgradeHeights {
+    overwinter: Some(347_500),
+    sapling: Some(419_200),
+};
+
+/// The network upgrade activation heights for the Zcash testnet.
+pub const TEST_NETWORK_UPGRADES: UpgradeHeights = UpgradeHeights {
+    overwinter: Some(207_500),
+    sapling: Some(280_000),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::{NetworkUpgrade, MAIN_NETWORK_UPGRADES};
+
+    #[test]
+    fn nu_is_active() {
+        assert!(!MAIN_NETWORK_UPGRADES.is_active(NetworkUpgrade::Overwinter, 0));
+        assert!(!MAIN_NETWORK_UPGRADES.is_active(NetworkUpgrade::Overwinter, 347_499));
+        assert!(MAIN_NETWORK_UPGRADES.is_active(NetworkUpgrade::Overwinter, 347_500));
+        assert!(!MAIN_NETWORK_UPGRADES.is_active(NetworkUpgrade::Sapling, 419_199));
+        assert!(MAIN_NETWORK_UPGRADES.is_active(NetworkUpgrade::Sapling, 419_200));
+    }
+}
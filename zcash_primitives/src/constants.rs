@@ -0,0 +1,56 @@
+//! BLAKE2b personalization strings used by the Zcash protocol.
+//!
+//! These are collected here so that other implementations of the protocol (such as
+//! indexers, or hardware wallet firmware) can refer to them directly.
+
+/// BLAKE2b Personalization for PRF^expand = BLAKE2b(sk || t)
+pub const PRF_EXPAND_PERSONALIZATION: &'static [u8; 16] = b"Zcash_ExpandSeed";
+
+/// BLAKE2b Personalization for the Sapling note encryption KDF
+pub const KDF_SAPLING_PERSONALIZATION: &'static [u8; 16] = b"Zcash_SaplingKDF";
+
+/// BLAKE2b Personalization for PRF^ock = BLAKE2b(ovk || cv || cmu || epk)
+pub const PRF_OCK_PERSONALIZATION: &'static [u8; 16] = b"Zcash_Derive_ock";
+
+// ZIP 32 personalizations
+/// BLAKE2b Personalization for the ZIP 32 Sapling master key
+pub const ZIP32_SAPLING_MASTER_PERSONALIZATION: &'static [u8; 16] = b"ZcashIP32Sapling";
+
+/// BLAKE2b Personalization for ZIP 32 Sapling full viewing key fingerprints
+pub const ZIP32_SAPLING_FVFP_PERSONALIZATION: &'static [u8; 16] = b"ZcashSaplingFVFP";
+
+/// BLAKE2b Personalization for ZIP 32 Sapling internal key derivation
+pub const ZIP32_SAPLING_INT_PERSONALIZATION: &'static [u8; 16] = b"Zcash_SaplingInt";
+
+// Transaction signature hash personalizations (ZIP 143 and ZIP 243)
+/// Prefix of the BLAKE2b Personalization for the signature hash. The consensus
+/// branch ID is appended as a 32-bit little-endian integer.
+pub const ZCASH_SIGHASH_PERSONALIZATION_PREFIX: &'static [u8; 12] = b"ZcashSigHash";
+
+/// BLAKE2b Personalization for hashPrevouts
+pub const ZCASH_PREVOUTS_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashPrevoutHash";
+
+/// BLAKE2b Personalization for hashSequence
+pub const ZCASH_SEQUENCE_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashSequencHash";
+
+/// BLAKE2b Personalization for hashOutputs
+pub const ZCASH_OUTPUTS_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashOutputsHash";
+
+/// BLAKE2b Personalization for hashJoinSplits
+pub const ZCASH_JOINSPLITS_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashJSplitsHash";
+
+/// BLAKE2b Personalization for hashShieldedSpends
+pub const ZCASH_SHIELDED_SPENDS_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashSSpendsHash";
+
+/// BLAKE2b Personalization for hashShieldedOutputs
+pub const ZCASH_SHIELDED_OUTPUTS_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashSOutputHash";
+
+/// BLAKE2b Personalization for hashTzeInputs in the experimental ZFUTURE format
+pub const ZCASH_TZE_INPUTS_HASH_PERSONALIZATION: &'static [u8; 16] = b"Zcash_TzeInsHash";
+
+/// BLAKE2b Personalization for hashTzeOutputs in the experimental ZFUTURE format
+pub const ZCASH_TZE_OUTPUTS_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashTzeOutsHash";
+
+// Personalizations for non-consensus protocols
+/// BLAKE2b Personalization for the challenge of an incoming viewing key ownership proof
+pub const IVK_PROOF_PERSONALIZATION: &'static [u8; 16] = b"Zcash_IvkPoK_Sig";
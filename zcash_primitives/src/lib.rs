@@ -14,9 +14,16 @@ extern crate rand_os;
 extern crate sapling_crypto;
 extern crate sha2;
 
+#[cfg(test)]
+extern crate rand_xorshift;
+
 use sapling_crypto::jubjub::JubjubBls12;
 
+pub mod address_proof;
+pub mod balance_report;
 pub mod block;
+pub mod consensus;
+pub mod constants;
 pub mod keys;
 pub mod merkle_tree;
 pub mod note_encryption;
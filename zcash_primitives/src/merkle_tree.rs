@@ -1,6 +1,6 @@
 //! Implementation of a Merkle tree of commitments used to prove the existence of notes.
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::collections::VecDeque;
 use std::io::{self, Read, Write};
 use std::iter;
@@ -19,6 +19,17 @@ pub trait Hashable: Clone + Copy {
     /// Returns the parent node within the tree of the two given nodes.
     fn combine(usize, &Self, &Self) -> Self;
 
+    /// Returns the parent nodes within the tree of each of the given pairs of nodes.
+    ///
+    /// The default implementation calls `combine` for each pair; implementations may
+    /// override it if a batch of pairs can be hashed more cheaply.
+    fn combine_many(depth: usize, pairs: &[(Self, Self)]) -> Vec<Self> {
+        pairs
+            .iter()
+            .map(|(lhs, rhs)| Self::combine(depth, lhs, rhs))
+            .collect()
+    }
+
     /// Returns a blank leaf node.
     fn blank() -> Self;
 
@@ -44,6 +55,63 @@ impl<Node: Hashable> PathFiller<Node> {
     }
 }
 
+/// The roots of every complete, aligned subtree within a contiguous range of leaves.
+struct SubtreeRoots<Node: Hashable> {
+    start: usize,
+    end: usize,
+    /// `levels[d][i]` is the root of the depth-`d` subtree at index
+    /// `first_index(start, d) + i`.
+    levels: Vec<Vec<Node>>,
+}
+
+/// Returns the index of the first depth-`depth` subtree starting at or after `pos`.
+fn first_index(pos: usize, depth: usize) -> usize {
+    (pos + (1 << depth) - 1) >> depth
+}
+
+impl<Node: Hashable> SubtreeRoots<Node> {
+    fn new(start: usize, nodes: &[Node]) -> Self {
+        let mut levels = vec![nodes.to_vec()];
+
+        loop {
+            let depth = levels.len() - 1;
+            let first = first_index(start, depth);
+            let next = {
+                let level = &levels[depth];
+                let pairs = (first_index(start, depth + 1)..)
+                    .map(|j| (2 * j - first, 2 * j + 1 - first))
+                    .take_while(|&(_, r)| r < level.len())
+                    .map(|(l, r)| (level[l], level[r]))
+                    .collect::<Vec<_>>();
+                Node::combine_many(depth, &pairs)
+            };
+
+            if next.is_empty() {
+                break;
+            }
+            levels.push(next);
+        }
+
+        SubtreeRoots {
+            start,
+            end: start + nodes.len(),
+            levels,
+        }
+    }
+
+    /// Returns the root of the depth-`depth` subtree starting at `pos`, if it lies
+    /// entirely within this range.
+    fn get(&self, depth: usize, pos: usize) -> Option<Node> {
+        let level = self.levels.get(depth)?;
+        if pos < self.start || pos + (1 << depth) > self.end {
+            return None;
+        }
+        level
+            .get((pos >> depth) - first_index(self.start, depth))
+            .cloned()
+    }
+}
+
 /// A Merkle tree of note commitments.
 ///
 /// The depth of the Merkle tree is fixed at 32, equal to the depth of the Sapling
@@ -104,6 +172,23 @@ impl<Node: Hashable> CommitmentTree<Node> {
         )
     }
 
+    /// Returns the part of this tree's frontier that lies below the given depth.
+    ///
+    /// This is the state of a depth-`depth` tree containing only the leaves of this
+    /// tree's last, incomplete depth-`depth` subtree.
+    fn truncated(&self, depth: usize) -> Self {
+        let mut parents: Vec<_> = self.parents.iter().take(depth - 1).cloned().collect();
+        while let Some(None) = parents.last() {
+            parents.pop();
+        }
+
+        CommitmentTree {
+            left: self.left,
+            right: self.right,
+            parents,
+        }
+    }
+
     fn is_complete(&self, depth: usize) -> bool {
         self.left.is_some()
             && self.right.is_some()
@@ -296,10 +381,19 @@ impl<Node: Hashable> IncrementalWitness<Node> {
         PathFiller { queue }
     }
 
+    /// Returns the size of the underlying tree that this witness is tracking.
+    fn tree_size(&self) -> usize {
+        (0..self.filled.len()).fold(self.tree.size(), |acc, i| acc + (1 << self.slot_depth(i)))
+            + self.cursor.as_ref().map(|c| c.size()).unwrap_or(0)
+    }
+
     /// Finds the next "depth" of an unfilled subtree.
     fn next_depth(&self) -> usize {
-        let mut skip = self.filled.len();
+        self.slot_depth(self.filled.len())
+    }
 
+    /// Finds the "depth" of the subtree that fills the given slot of the path.
+    fn slot_depth(&self, mut skip: usize) -> usize {
         if self.tree.left.is_none() {
             if skip > 0 {
                 skip -= 1;
@@ -370,6 +464,99 @@ impl<Node: Hashable> IncrementalWitness<Node> {
         Ok(())
     }
 
+    /// Appends a batch of leaf nodes to the given tree, and tracks them in each of the
+    /// given witnesses of that tree.
+    ///
+    /// This is equivalent to appending each node to the tree and to every witness in
+    /// turn, but the roots of subtrees within the batch are computed once and shared
+    /// between all of the witnesses, instead of being recomputed by each of them.
+    ///
+    /// Returns an error if the tree would become full, or if any of the witnesses is not
+    /// tracking the given tree. In either case, neither the tree nor the witnesses are
+    /// modified.
+    pub fn append_batch<'a, I>(
+        tree: &mut CommitmentTree<Node>,
+        witnesses: I,
+        nodes: &[Node],
+    ) -> Result<(), ()>
+    where
+        I: IntoIterator<Item = &'a mut IncrementalWitness<Node>>,
+        Node: 'a,
+    {
+        Self::append_batch_inner(tree, witnesses, nodes, SAPLING_COMMITMENT_TREE_DEPTH)
+    }
+
+    fn append_batch_inner<'a, I>(
+        tree: &mut CommitmentTree<Node>,
+        witnesses: I,
+        nodes: &[Node],
+        depth: usize,
+    ) -> Result<(), ()>
+    where
+        I: IntoIterator<Item = &'a mut IncrementalWitness<Node>>,
+        Node: 'a,
+    {
+        let start = tree.size();
+        let witnesses: Vec<_> = witnesses.into_iter().collect();
+        if witnesses.iter().any(|witness| witness.tree_size() != start) {
+            return Err(());
+        }
+
+        let mut updated = tree.clone();
+        for node in nodes {
+            updated.append_inner(*node, depth)?;
+        }
+
+        let roots = SubtreeRoots::new(start, nodes);
+        for witness in witnesses {
+            witness.fill_from(&roots, &updated);
+        }
+
+        *tree = updated;
+        Ok(())
+    }
+
+    /// Fills as many subtrees as possible from the given roots, and then sets the
+    /// cursor from the frontier of the updated tree.
+    fn fill_from(&mut self, roots: &SubtreeRoots<Node>, updated: &CommitmentTree<Node>) {
+        let mut pos = roots.start;
+
+        if let Some(cursor) = self.cursor.take() {
+            // Complete the cursor with the subtrees that come after its last leaf.
+            let cursor_start = pos - cursor.size();
+            let cursor_end = cursor_start + (1 << self.cursor_depth);
+            if cursor_end > roots.end {
+                self.cursor = Some(updated.truncated(self.cursor_depth));
+                return;
+            }
+
+            let mut queue = VecDeque::new();
+            while pos < cursor_end {
+                let d = (pos - cursor_start).trailing_zeros() as usize;
+                queue.push_back(roots.get(d, pos).expect("subtree is within the batch"));
+                pos += 1 << d;
+            }
+            self.filled
+                .push(cursor.root_inner(self.cursor_depth, PathFiller { queue }));
+        }
+
+        while pos < roots.end {
+            self.cursor_depth = self.next_depth();
+            match roots.get(self.cursor_depth, pos) {
+                Some(node) => {
+                    self.filled.push(node);
+                    pos += 1 << self.cursor_depth;
+                }
+                None => {
+                    // The next subtree extends past the end of the batch, so it is
+                    // the incomplete subtree at the end of the updated tree.
+                    self.cursor = Some(updated.truncated(self.cursor_depth));
+                    break;
+                }
+            }
+        }
+    }
+
     /// Returns the current root of the tree corresponding to the witness.
     pub fn root(&self) -> Node {
         self.root_inner(SAPLING_COMMITMENT_TREE_DEPTH)
@@ -435,6 +622,44 @@ impl<Node: Hashable> CommitmentTreeWitness<Node> {
         }
     }
 
+    /// Returns the root of the tree that this witness is for, given the leaf at its
+    /// position, or `None` if the witness is incomplete.
+    pub fn root(&self, leaf: Node) -> Option<Node> {
+        self.auth_path
+            .iter()
+            .enumerate()
+            .try_fold(leaf, |cur, (depth, elem)| {
+                elem.map(|(sibling, cur_is_right)| {
+                    if cur_is_right {
+                        Node::combine(depth, &sibling, &cur)
+                    } else {
+                        Node::combine(depth, &cur, &sibling)
+                    }
+                })
+            })
+    }
+
+    /// Writes this witness in the serialized form read by
+    /// [`CommitmentTreeWitness::from_slice`].
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u8(self.auth_path.len() as u8)?;
+        for elem in self.auth_path.iter().rev() {
+            match elem {
+                Some((sibling, _)) => {
+                    writer.write_u8(32)?;
+                    sibling.write(&mut writer)?;
+                }
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Cannot serialize an incomplete witness",
+                    ))
+                }
+            }
+        }
+        writer.write_u64::<LittleEndian>(self.position)
+    }
+
     /// Reads a witness from its serialized form.
     pub fn from_slice(witness: &[u8]) -> Result<Self, ()> {
         Self::from_slice_with_depth(witness, SAPLING_COMMITMENT_TREE_DEPTH)
@@ -483,6 +708,12 @@ impl<Node: Hashable> CommitmentTreeWitness<Node> {
             Err(_) => return Err(()),
         };
 
+        // The position must be in the tree, because only its low `depth` bits
+        // are used as the path directions
+        if position >> depth != 0 {
+            return Err(());
+        }
+
         // Given the position, let's finish constructing the authentication
         // path
         let mut tmp = position;
@@ -509,8 +740,9 @@ impl<Node: Hashable> CommitmentTreeWitness<Node> {
 #[cfg(test)]
 mod tests {
     use super::{CommitmentTree, CommitmentTreeWitness, Hashable, IncrementalWitness, PathFiller};
-    use sapling::Node;
+    use sapling::{Node, SAPLING_COMMITMENT_TREE_DEPTH};
 
+    use byteorder::{LittleEndian, WriteBytesExt};
     use ff::PrimeFieldRepr;
     use hex;
     use pairing::bls12_381::FrRepr;
@@ -1051,6 +1283,13 @@ mod tests {
                     )
                     .unwrap();
                     assert_eq!(path, expected);
+
+                    let mut path_ser = vec![];
+                    path.write(&mut path_ser).unwrap();
+                    assert_eq!(hex::encode(path_ser), paths[paths_i]);
+
+                    let leaf = witness.0.tree.right.or(witness.0.tree.left).unwrap();
+                    assert_eq!(path.root(leaf), Some(tree.root()));
                     paths_i += 1;
                 }
 
@@ -1071,4 +1310,152 @@ mod tests {
             assert!(witness.append(node).is_err());
         }
     }
+
+    fn assert_batch_matches_naive(depth: usize, prefix: u64, batch: u64) {
+        let node = |i: u64| Node::new(FrRepr([i, 0, 0, 0]));
+
+        // Witness every leaf of the initial tree
+        let mut tree = CommitmentTree::new();
+        let mut witnesses: Vec<IncrementalWitness<Node>> = vec![];
+        for i in 0..prefix {
+            for witness in witnesses.iter_mut() {
+                witness.append_inner(node(i), depth).unwrap();
+            }
+            tree.append_inner(node(i), depth).unwrap();
+            witnesses.push(IncrementalWitness::from_tree(&tree));
+        }
+
+        let nodes: Vec<_> = (prefix..prefix + batch).map(node).collect();
+
+        let mut naive_tree = tree.clone();
+        let mut naive_witnesses = witnesses.clone();
+        for n in &nodes {
+            naive_tree.append_inner(*n, depth).unwrap();
+            for witness in naive_witnesses.iter_mut() {
+                witness.append_inner(*n, depth).unwrap();
+            }
+        }
+
+        IncrementalWitness::append_batch_inner(&mut tree, witnesses.iter_mut(), &nodes, depth)
+            .unwrap();
+
+        let mut tree_ser = vec![];
+        let mut naive_tree_ser = vec![];
+        tree.write(&mut tree_ser).unwrap();
+        naive_tree.write(&mut naive_tree_ser).unwrap();
+        assert_eq!(tree_ser, naive_tree_ser);
+
+        for (witness, naive) in witnesses.iter().zip(naive_witnesses.iter()) {
+            let mut witness_ser = vec![];
+            let mut naive_ser = vec![];
+            witness.write(&mut witness_ser).unwrap();
+            naive.write(&mut naive_ser).unwrap();
+            assert_eq!(witness_ser, naive_ser);
+            assert_eq!(witness.cursor_depth, naive.cursor_depth);
+            assert_eq!(
+                witness.root_inner(depth),
+                naive_tree.root_inner(depth, PathFiller::empty())
+            );
+            assert_eq!(witness.path_inner(depth), naive.path_inner(depth));
+        }
+    }
+
+    #[test]
+    fn append_batch_matches_individual_appends() {
+        for prefix in 1..(1 << TESTING_DEPTH) {
+            for batch in 0..((1 << TESTING_DEPTH) - prefix + 1) {
+                assert_batch_matches_naive(TESTING_DEPTH, prefix, batch);
+            }
+        }
+
+        // Exercise the cursors of a full-depth tree
+        assert_batch_matches_naive(SAPLING_COMMITMENT_TREE_DEPTH, 5, 37);
+    }
+
+    #[test]
+    fn append_batch_rejects_full_tree() {
+        let node = |i: u64| Node::new(FrRepr([i, 0, 0, 0]));
+
+        let mut tree = CommitmentTree::new();
+        tree.append_inner(node(0), TESTING_DEPTH).unwrap();
+        let mut witness = IncrementalWitness::from_tree(&tree);
+
+        let nodes: Vec<_> = (1..(1 << TESTING_DEPTH) + 1).map(node).collect();
+        assert!(IncrementalWitness::append_batch_inner(
+            &mut tree,
+            Some(&mut witness),
+            &nodes,
+            TESTING_DEPTH
+        )
+        .is_err());
+
+        // Neither the tree nor the witness were modified
+        assert_eq!(tree.size(), 1);
+        assert_eq!(witness.tree_size(), 1);
+    }
+
+    #[test]
+    fn append_batch_rejects_stale_witness() {
+        let node = |i: u64| Node::new(FrRepr([i, 0, 0, 0]));
+        let ser = |witness: &IncrementalWitness<Node>| {
+            let mut data = vec![];
+            witness.write(&mut data).unwrap();
+            data
+        };
+
+        let mut tree = CommitmentTree::new();
+        tree.append_inner(node(0), TESTING_DEPTH).unwrap();
+        let mut witness = IncrementalWitness::from_tree(&tree);
+        // This witness will not see the second leaf.
+        let mut stale = IncrementalWitness::from_tree(&tree);
+        tree.append_inner(node(1), TESTING_DEPTH).unwrap();
+        witness.append_inner(node(1), TESTING_DEPTH).unwrap();
+        let mut current = IncrementalWitness::from_tree(&tree);
+
+        let (witness_ser, current_ser) = (ser(&witness), ser(&current));
+        let nodes: Vec<_> = (2..5).map(node).collect();
+        assert!(IncrementalWitness::append_batch_inner(
+            &mut tree,
+            vec![&mut witness, &mut current, &mut stale],
+            &nodes,
+            TESTING_DEPTH
+        )
+        .is_err());
+
+        // Neither the tree nor any of the witnesses were modified, including those
+        // before the stale one.
+        assert_eq!(tree.size(), 2);
+        assert_eq!(ser(&witness), witness_ser);
+        assert_eq!(ser(&current), current_ser);
+        assert_eq!(stale.tree_size(), 1);
+    }
+
+    #[test]
+    fn witness_position_out_of_range() {
+        let node = |i: u64| Node::new(FrRepr([i, 0, 0, 0]));
+
+        let mut tree = CommitmentTree::new();
+        tree.append_inner(node(0), TESTING_DEPTH).unwrap();
+        tree.append_inner(node(1), TESTING_DEPTH).unwrap();
+        let witness = IncrementalWitness::from_tree(&tree);
+
+        let mut path_ser = vec![];
+        witness
+            .path_inner(TESTING_DEPTH)
+            .unwrap()
+            .write(&mut path_ser)
+            .unwrap();
+        assert!(
+            CommitmentTreeWitness::<Node>::from_slice_with_depth(&path_ser, TESTING_DEPTH).is_ok()
+        );
+
+        // The same path directions, at a position outside the tree
+        let len = path_ser.len();
+        (&mut path_ser[len - 8..])
+            .write_u64::<LittleEndian>(1 + (1 << TESTING_DEPTH))
+            .unwrap();
+        assert!(
+            CommitmentTreeWitness::<Node>::from_slice_with_depth(&path_ser, TESTING_DEPTH).is_err()
+        );
+    }
 }
@@ -11,17 +11,16 @@ use sapling_crypto::{
     jubjub::{
         edwards,
         fs::{Fs, FsRepr},
-        PrimeOrder, ToUniform, Unknown,
+        JubjubBls12, PrimeOrder, ToUniform, Unknown,
     },
     primitives::{Diversifier, Note, PaymentAddress},
 };
 use std::fmt;
 use std::str;
 
-use crate::{keys::OutgoingViewingKey, JUBJUB};
+use crate::keys::OutgoingViewingKey;
 
-pub const KDF_SAPLING_PERSONALIZATION: &'static [u8; 16] = b"Zcash_SaplingKDF";
-pub const PRF_OCK_PERSONALIZATION: &'static [u8; 16] = b"Zcash_Derive_ock";
+pub use crate::constants::{KDF_SAPLING_PERSONALIZATION, PRF_OCK_PERSONALIZATION};
 
 const COMPACT_NOTE_SIZE: usize = (
     1  + // version
@@ -148,17 +147,21 @@ fn generate_esk() -> Fs {
 /// Sapling key agreement for note encryption.
 ///
 /// Implements section 5.4.4.3 of the Zcash Protocol Specification.
-pub fn sapling_ka_agree<'a, P>(esk: &Fs, pk_d: &'a P) -> edwards::Point<Bls12, PrimeOrder>
+pub fn sapling_ka_agree<'a, P>(
+    esk: &Fs,
+    pk_d: &'a P,
+    params: &JubjubBls12,
+) -> edwards::Point<Bls12, PrimeOrder>
 where
     edwards::Point<Bls12, Unknown>: From<&'a P>,
 {
     let p: edwards::Point<Bls12, Unknown> = pk_d.into();
 
     // Multiply by 8
-    let p = p.mul_by_cofactor(&JUBJUB);
+    let p = p.mul_by_cofactor(params);
 
     // Multiply by esk
-    p.mul(*esk, &JUBJUB)
+    p.mul(*esk, params)
 }
 
 /// Sapling KDF for note encryption.
@@ -247,37 +250,86 @@ fn prf_ock(
 /// let note = to.create_note(value, rcv, &JUBJUB).unwrap();
 /// let cmu = note.cm(&JUBJUB);
 ///
-/// let enc = SaplingNoteEncryption::new(ovk, note, to, Memo::default());
+/// let enc = SaplingNoteEncryption::new(Some(ovk), note, to, Memo::default(), &JUBJUB);
 /// let encCiphertext = enc.encrypt_note_plaintext();
 /// let outCiphertext = enc.encrypt_outgoing_plaintext(&cv.cm(&JUBJUB).into(), &cmu);
 /// ```
 pub struct SaplingNoteEncryption {
     epk: edwards::Point<Bls12, PrimeOrder>,
     esk: Fs,
+    /// The symmetric key for `encCiphertext`, derived from `esk` and `pk_d`.
+    key_enc: [u8; 32],
     note: Note<Bls12>,
     to: PaymentAddress<Bls12>,
     memo: Memo,
-    ovk: OutgoingViewingKey,
+    ovk: Option<OutgoingViewingKey>,
+    /// The random key and plaintext used for `outCiphertext` when `ovk` is `None`.
+    random_out: Option<([u8; 32], [u8; OUT_PLAINTEXT_SIZE])>,
 }
 
 impl SaplingNoteEncryption {
     /// Creates a new encryption context for the given note.
+    ///
+    /// If `ovk` is `None`, the note will not be recoverable by the sender: its
+    /// `outCiphertext` will be the encryption of random data under a random key, as
+    /// specified in section 4.6.2 of the Zcash Protocol Specification.
+    ///
+    /// `params` are only used here: `epk` and the key for `encCiphertext` are derived
+    /// up front, so the `encrypt_*` methods do not need them.
     pub fn new(
-        ovk: OutgoingViewingKey,
+        ovk: Option<OutgoingViewingKey>,
         note: Note<Bls12>,
         to: PaymentAddress<Bls12>,
         memo: Memo,
+        params: &JubjubBls12,
     ) -> SaplingNoteEncryption {
-        let esk = generate_esk();
-        let epk = note.g_d.mul(esk, &JUBJUB);
+        Self::with_esk(generate_esk(), ovk, note, to, memo, params, &mut OsRng)
+    }
+
+    /// Creates a new encryption context for the given note, using the given ephemeral
+    /// secret key.
+    ///
+    /// `esk` MUST be sampled uniformly at random and never reused. This is intended for
+    /// callers that need to recreate the same ciphertexts, such as a signer checking the
+    /// outputs of a transaction created by a separate prover, or for tests.
+    ///
+    /// If `ovk` is `None`, the random key and plaintext for `outCiphertext` are drawn
+    /// from `rng` here, so that they can be recreated along with `esk`.
+    pub fn with_esk<R: RngCore>(
+        esk: Fs,
+        ovk: Option<OutgoingViewingKey>,
+        note: Note<Bls12>,
+        to: PaymentAddress<Bls12>,
+        memo: Memo,
+        params: &JubjubBls12,
+        rng: &mut R,
+    ) -> SaplingNoteEncryption {
+        let epk = note.g_d.mul(esk, params);
+
+        let shared_secret = sapling_ka_agree(&esk, &to.pk_d, params);
+        let mut key_enc = [0; 32];
+        key_enc.copy_from_slice(kdf_sapling(shared_secret, &epk).as_bytes());
+
+        let random_out = match ovk {
+            Some(_) => None,
+            None => {
+                let mut key = [0u8; 32];
+                let mut input = [0u8; OUT_PLAINTEXT_SIZE];
+                rng.fill_bytes(&mut key);
+                rng.fill_bytes(&mut input);
+                Some((key, input))
+            }
+        };
 
         SaplingNoteEncryption {
             epk,
             esk,
+            key_enc,
             note,
             to,
             memo,
             ovk,
+            random_out,
         }
     }
 
@@ -293,9 +345,6 @@ impl SaplingNoteEncryption {
 
     /// Generates `encCiphertext` for this note.
     pub fn encrypt_note_plaintext(&self) -> [u8; ENC_CIPHERTEXT_SIZE] {
-        let shared_secret = sapling_ka_agree(&self.esk, &self.to.pk_d);
-        let key = kdf_sapling(shared_secret, &self.epk);
-
         // Note plaintext encoding is defined in section 5.5 of the Zcash Protocol
         // Specification.
         let mut input = [0; NOTE_PLAINTEXT_SIZE];
@@ -314,7 +363,7 @@ impl SaplingNoteEncryption {
         let mut output = [0u8; ENC_CIPHERTEXT_SIZE];
         assert_eq!(
             ChachaPolyIetf::aead_cipher()
-                .seal_to(&mut output, &input, &[], &key.as_bytes(), &[0u8; 12])
+                .seal_to(&mut output, &input, &[], &self.key_enc, &[0u8; 12])
                 .unwrap(),
             ENC_CIPHERTEXT_SIZE
         );
@@ -328,19 +377,32 @@ impl SaplingNoteEncryption {
         cv: &edwards::Point<Bls12, Unknown>,
         cmu: &Fr,
     ) -> [u8; OUT_CIPHERTEXT_SIZE] {
-        let key = prf_ock(&self.ovk, &cv, &cmu, &self.epk);
-
+        let mut key = [0u8; 32];
         let mut input = [0u8; OUT_PLAINTEXT_SIZE];
-        self.note.pk_d.write(&mut input[0..32]).unwrap();
-        self.esk
-            .into_repr()
-            .write_le(&mut input[32..OUT_PLAINTEXT_SIZE])
-            .unwrap();
+        match &self.ovk {
+            Some(ovk) => {
+                key.copy_from_slice(prf_ock(ovk, &cv, &cmu, &self.epk).as_bytes());
+
+                self.note.pk_d.write(&mut input[0..32]).unwrap();
+                self.esk
+                    .into_repr()
+                    .write_le(&mut input[32..OUT_PLAINTEXT_SIZE])
+                    .unwrap();
+            }
+            None => {
+                let (random_key, random_input) = self
+                    .random_out
+                    .as_ref()
+                    .expect("random_out is set when ovk is None");
+                key.copy_from_slice(random_key);
+                input.copy_from_slice(random_input);
+            }
+        }
 
         let mut output = [0u8; OUT_CIPHERTEXT_SIZE];
         assert_eq!(
             ChachaPolyIetf::aead_cipher()
-                .seal_to(&mut output, &input, &[], key.as_bytes(), &[0u8; 12])
+                .seal_to(&mut output, &input, &[], &key, &[0u8; 12])
                 .unwrap(),
             OUT_CIPHERTEXT_SIZE
         );
@@ -353,6 +415,7 @@ fn parse_note_plaintext_without_memo(
     ivk: &Fs,
     cmu: &Fr,
     plaintext: &[u8],
+    params: &JubjubBls12,
 ) -> Option<(Note<Bls12>, PaymentAddress<Bls12>)> {
     // Check note plaintext version
     match plaintext[0] {
@@ -371,13 +434,13 @@ fn parse_note_plaintext_without_memo(
 
     let diversifier = Diversifier(d);
     let pk_d = diversifier
-        .g_d::<Bls12>(&JUBJUB)?
-        .mul(ivk.into_repr(), &JUBJUB);
+        .g_d::<Bls12>(params)?
+        .mul(ivk.into_repr(), params);
 
     let to = PaymentAddress { pk_d, diversifier };
-    let note = to.create_note(v, rcm, &JUBJUB).unwrap();
+    let note = to.create_note(v, rcm, params).unwrap();
 
-    if note.cm(&JUBJUB) != *cmu {
+    if note.cm(params) != *cmu {
         // Published commitment doesn't match calculated commitment
         return None;
     }
@@ -397,10 +460,11 @@ pub fn try_sapling_note_decryption(
     epk: &edwards::Point<Bls12, PrimeOrder>,
     cmu: &Fr,
     enc_ciphertext: &[u8],
+    params: &JubjubBls12,
 ) -> Option<(Note<Bls12>, PaymentAddress<Bls12>, Memo)> {
     assert_eq!(enc_ciphertext.len(), ENC_CIPHERTEXT_SIZE);
 
-    let shared_secret = sapling_ka_agree(ivk, epk);
+    let shared_secret = sapling_ka_agree(ivk, epk, params);
     let key = kdf_sapling(shared_secret, &epk);
 
     let mut plaintext = [0; ENC_CIPHERTEXT_SIZE];
@@ -417,7 +481,7 @@ pub fn try_sapling_note_decryption(
         NOTE_PLAINTEXT_SIZE
     );
 
-    let (note, to) = parse_note_plaintext_without_memo(ivk, cmu, &plaintext)?;
+    let (note, to) = parse_note_plaintext_without_memo(ivk, cmu, &plaintext, params)?;
 
     let mut memo = [0u8; 512];
     memo.copy_from_slice(&plaintext[COMPACT_NOTE_SIZE..NOTE_PLAINTEXT_SIZE]);
@@ -439,10 +503,11 @@ pub fn try_sapling_compact_note_decryption(
     epk: &edwards::Point<Bls12, PrimeOrder>,
     cmu: &Fr,
     enc_ciphertext: &[u8],
+    params: &JubjubBls12,
 ) -> Option<(Note<Bls12>, PaymentAddress<Bls12>)> {
     assert_eq!(enc_ciphertext.len(), COMPACT_NOTE_SIZE);
 
-    let shared_secret = sapling_ka_agree(ivk, epk);
+    let shared_secret = sapling_ka_agree(ivk, epk, params);
     let key = kdf_sapling(shared_secret, &epk);
 
     // Prefix plaintext with 64 zero-bytes to skip over Poly1305 keying output
@@ -461,7 +526,7 @@ pub fn try_sapling_compact_note_decryption(
         CHACHA20_BLOCK_SIZE + COMPACT_NOTE_SIZE
     );
 
-    parse_note_plaintext_without_memo(ivk, cmu, &plaintext[CHACHA20_BLOCK_SIZE..])
+    parse_note_plaintext_without_memo(ivk, cmu, &plaintext[CHACHA20_BLOCK_SIZE..], params)
 }
 
 /// Recovery of the full note plaintext by the sender.
@@ -478,6 +543,7 @@ pub fn try_sapling_output_recovery(
     epk: &edwards::Point<Bls12, PrimeOrder>,
     enc_ciphertext: &[u8],
     out_ciphertext: &[u8],
+    params: &JubjubBls12,
 ) -> Option<(Note<Bls12>, PaymentAddress<Bls12>, Memo)> {
     assert_eq!(enc_ciphertext.len(), ENC_CIPHERTEXT_SIZE);
     assert_eq!(out_ciphertext.len(), OUT_CIPHERTEXT_SIZE);
@@ -492,15 +558,15 @@ pub fn try_sapling_output_recovery(
         OUT_PLAINTEXT_SIZE
     );
 
-    let pk_d = edwards::Point::<Bls12, _>::read(&op[0..32], &JUBJUB)
+    let pk_d = edwards::Point::<Bls12, _>::read(&op[0..32], params)
         .ok()?
-        .as_prime_order(&JUBJUB)?;
+        .as_prime_order(params)?;
 
     let mut esk = FsRepr::default();
     esk.read_le(&op[32..OUT_PLAINTEXT_SIZE]).ok()?;
     let esk = Fs::from_repr(esk).ok()?;
 
-    let shared_secret = sapling_ka_agree(&esk, &pk_d);
+    let shared_secret = sapling_ka_agree(&esk, &pk_d, params);
     let key = kdf_sapling(shared_secret, &epk);
 
     let mut plaintext = [0; ENC_CIPHERTEXT_SIZE];
@@ -537,8 +603,8 @@ pub fn try_sapling_output_recovery(
 
     let diversifier = Diversifier(d);
     if diversifier
-        .g_d::<Bls12>(&JUBJUB)?
-        .mul(esk.into_repr(), &JUBJUB)
+        .g_d::<Bls12>(params)?
+        .mul(esk.into_repr(), params)
         != *epk
     {
         // Published epk doesn't match calculated epk
@@ -546,9 +612,9 @@ pub fn try_sapling_output_recovery(
     }
 
     let to = PaymentAddress { pk_d, diversifier };
-    let note = to.create_note(v, rcm, &JUBJUB).unwrap();
+    let note = to.create_note(v, rcm, params).unwrap();
 
-    if note.cm(&JUBJUB) != *cmu {
+    if note.cm(params) != *cmu {
         // Published commitment doesn't match calculated commitment
         return None;
     }
@@ -561,8 +627,9 @@ mod tests {
     use crypto_api_chachapoly::ChachaPolyIetf;
     use ff::{Field, PrimeField, PrimeFieldRepr};
     use pairing::bls12_381::{Bls12, Fr, FrRepr};
-    use rand_core::RngCore;
+    use rand_core::{RngCore, SeedableRng};
     use rand_os::OsRng;
+    use rand_xorshift::XorShiftRng;
     use sapling_crypto::{
         jubjub::{
             edwards,
@@ -724,17 +791,18 @@ mod tests {
         let cmu = note.cm(&JUBJUB);
 
         let ovk = OutgoingViewingKey([0; 32]);
-        let ne = SaplingNoteEncryption::new(ovk, note, pa, Memo([0; 512]));
+        let ne = SaplingNoteEncryption::new(Some(ovk), note, pa, Memo([0; 512]), &JUBJUB);
         let epk = ne.epk();
         let enc_ciphertext = ne.encrypt_note_plaintext();
         let out_ciphertext = ne.encrypt_outgoing_plaintext(&cv, &cmu);
 
-        assert!(try_sapling_note_decryption(&ivk, epk, &cmu, &enc_ciphertext).is_some());
+        assert!(try_sapling_note_decryption(&ivk, epk, &cmu, &enc_ciphertext, &JUBJUB).is_some());
         assert!(try_sapling_compact_note_decryption(
             &ivk,
             epk,
             &cmu,
-            &enc_ciphertext[..COMPACT_NOTE_SIZE]
+            &enc_ciphertext[..COMPACT_NOTE_SIZE],
+            &JUBJUB
         )
         .is_some());
         assert!(try_sapling_output_recovery(
@@ -743,7 +811,8 @@ mod tests {
             &cmu,
             &epk,
             &enc_ciphertext,
-            &out_ciphertext
+            &out_ciphertext,
+            &JUBJUB
         )
         .is_some());
 
@@ -786,7 +855,7 @@ mod tests {
         esk.read_le(&op[32..OUT_PLAINTEXT_SIZE]).unwrap();
         let esk = Fs::from_repr(esk).unwrap();
 
-        let shared_secret = sapling_ka_agree(&esk, &pk_d);
+        let shared_secret = sapling_ka_agree(&esk, &pk_d, &JUBJUB);
         let key = kdf_sapling(shared_secret, &epk);
 
         let mut plaintext = {
@@ -853,7 +922,13 @@ mod tests {
         let (_, _, _, cmu, epk, enc_ciphertext, _) = random_enc_ciphertext(&mut rng);
 
         assert_eq!(
-            try_sapling_note_decryption(&Fs::random(&mut rng), &epk, &cmu, &enc_ciphertext),
+            try_sapling_note_decryption(
+                &Fs::random(&mut rng),
+                &epk,
+                &cmu,
+                &enc_ciphertext,
+                &JUBJUB
+            ),
             None
         );
     }
@@ -869,7 +944,8 @@ mod tests {
                 &ivk,
                 &edwards::Point::<Bls12, _>::rand(&mut rng, &JUBJUB).mul_by_cofactor(&JUBJUB),
                 &cmu,
-                &enc_ciphertext
+                &enc_ciphertext,
+                &JUBJUB
             ),
             None
         );
@@ -882,7 +958,13 @@ mod tests {
         let (_, ivk, _, _, epk, enc_ciphertext, _) = random_enc_ciphertext(&mut rng);
 
         assert_eq!(
-            try_sapling_note_decryption(&ivk, &epk, &Fr::random(&mut rng), &enc_ciphertext),
+            try_sapling_note_decryption(
+                &ivk,
+                &epk,
+                &Fr::random(&mut rng),
+                &enc_ciphertext,
+                &JUBJUB
+            ),
             None
         );
     }
@@ -895,7 +977,7 @@ mod tests {
 
         enc_ciphertext[ENC_CIPHERTEXT_SIZE - 1] ^= 0xff;
         assert_eq!(
-            try_sapling_note_decryption(&ivk, &epk, &cmu, &enc_ciphertext),
+            try_sapling_note_decryption(&ivk, &epk, &cmu, &enc_ciphertext, &JUBJUB),
             None
         );
     }
@@ -917,7 +999,7 @@ mod tests {
             |pt| pt[0] = 0x02,
         );
         assert_eq!(
-            try_sapling_note_decryption(&ivk, &epk, &cmu, &enc_ciphertext),
+            try_sapling_note_decryption(&ivk, &epk, &cmu, &enc_ciphertext, &JUBJUB),
             None
         );
     }
@@ -939,7 +1021,7 @@ mod tests {
             |pt| pt[1..12].copy_from_slice(&find_invalid_diversifier().0),
         );
         assert_eq!(
-            try_sapling_note_decryption(&ivk, &epk, &cmu, &enc_ciphertext),
+            try_sapling_note_decryption(&ivk, &epk, &cmu, &enc_ciphertext, &JUBJUB),
             None
         );
     }
@@ -961,7 +1043,7 @@ mod tests {
             |pt| pt[1..12].copy_from_slice(&find_valid_diversifier().0),
         );
         assert_eq!(
-            try_sapling_note_decryption(&ivk, &epk, &cmu, &enc_ciphertext),
+            try_sapling_note_decryption(&ivk, &epk, &cmu, &enc_ciphertext, &JUBJUB),
             None
         );
     }
@@ -977,7 +1059,8 @@ mod tests {
                 &Fs::random(&mut rng),
                 &epk,
                 &cmu,
-                &enc_ciphertext[..COMPACT_NOTE_SIZE]
+                &enc_ciphertext[..COMPACT_NOTE_SIZE],
+                &JUBJUB
             ),
             None
         );
@@ -994,7 +1077,8 @@ mod tests {
                 &ivk,
                 &edwards::Point::<Bls12, _>::rand(&mut rng, &JUBJUB).mul_by_cofactor(&JUBJUB),
                 &cmu,
-                &enc_ciphertext[..COMPACT_NOTE_SIZE]
+                &enc_ciphertext[..COMPACT_NOTE_SIZE],
+                &JUBJUB
             ),
             None
         );
@@ -1011,7 +1095,8 @@ mod tests {
                 &ivk,
                 &epk,
                 &Fr::random(&mut rng),
-                &enc_ciphertext[..COMPACT_NOTE_SIZE]
+                &enc_ciphertext[..COMPACT_NOTE_SIZE],
+                &JUBJUB
             ),
             None
         );
@@ -1038,7 +1123,8 @@ mod tests {
                 &ivk,
                 &epk,
                 &cmu,
-                &enc_ciphertext[..COMPACT_NOTE_SIZE]
+                &enc_ciphertext[..COMPACT_NOTE_SIZE],
+                &JUBJUB
             ),
             None
         );
@@ -1065,7 +1151,8 @@ mod tests {
                 &ivk,
                 &epk,
                 &cmu,
-                &enc_ciphertext[..COMPACT_NOTE_SIZE]
+                &enc_ciphertext[..COMPACT_NOTE_SIZE],
+                &JUBJUB
             ),
             None
         );
@@ -1092,7 +1179,8 @@ mod tests {
                 &ivk,
                 &epk,
                 &cmu,
-                &enc_ciphertext[..COMPACT_NOTE_SIZE]
+                &enc_ciphertext[..COMPACT_NOTE_SIZE],
+                &JUBJUB
             ),
             None
         );
@@ -1107,7 +1195,112 @@ mod tests {
 
         ovk.0[0] ^= 0xff;
         assert_eq!(
-            try_sapling_output_recovery(&ovk, &cv, &cmu, &epk, &enc_ciphertext, &out_ciphertext),
+            try_sapling_output_recovery(
+                &ovk,
+                &cv,
+                &cmu,
+                &epk,
+                &enc_ciphertext,
+                &out_ciphertext,
+                &JUBJUB
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn outgoing_plaintext_without_ovk_is_reproducible() {
+        let mut rng = OsRng;
+
+        let diversifier = Diversifier([0; 11]);
+        let ivk = Fs::random(&mut rng);
+        let pk_d = diversifier.g_d::<Bls12>(&JUBJUB).unwrap().mul(ivk, &JUBJUB);
+        let pa = PaymentAddress { diversifier, pk_d };
+
+        let value_commitment = ValueCommitment::<Bls12> {
+            value: 100,
+            randomness: Fs::random(&mut rng),
+        };
+        let cv = value_commitment.cm(&JUBJUB).into();
+        let note = pa.create_note(100, Fs::random(&mut rng), &JUBJUB).unwrap();
+        let cmu = note.cm(&JUBJUB);
+        let esk = Fs::random(&mut rng);
+
+        let encrypt = |seed| {
+            SaplingNoteEncryption::with_esk(
+                esk,
+                None,
+                note.clone(),
+                pa.clone(),
+                Memo::default(),
+                &JUBJUB,
+                &mut XorShiftRng::from_seed(seed),
+            )
+        };
+        let seed = [
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ];
+
+        // The same context always produces the same outCiphertext...
+        let ne = encrypt(seed);
+        let out_ciphertext = ne.encrypt_outgoing_plaintext(&cv, &cmu);
+        assert_eq!(
+            &ne.encrypt_outgoing_plaintext(&cv, &cmu)[..],
+            &out_ciphertext[..]
+        );
+
+        // ...as does a context recreated from the same esk and RNG seed.
+        assert_eq!(
+            &encrypt(seed).encrypt_outgoing_plaintext(&cv, &cmu)[..],
+            &out_ciphertext[..]
+        );
+
+        let mut other_seed = seed;
+        other_seed[0] ^= 1;
+        assert_ne!(
+            &encrypt(other_seed).encrypt_outgoing_plaintext(&cv, &cmu)[..],
+            &out_ciphertext[..]
+        );
+    }
+
+    #[test]
+    fn recovery_without_ovk() {
+        let mut rng = OsRng;
+
+        let diversifier = Diversifier([0; 11]);
+        let ivk = Fs::random(&mut rng);
+        let pk_d = diversifier.g_d::<Bls12>(&JUBJUB).unwrap().mul(ivk, &JUBJUB);
+        let pa = PaymentAddress { diversifier, pk_d };
+
+        let value_commitment = ValueCommitment::<Bls12> {
+            value: 100,
+            randomness: Fs::random(&mut rng),
+        };
+        let cv = value_commitment.cm(&JUBJUB).into();
+        let note = pa.create_note(100, Fs::random(&mut rng), &JUBJUB).unwrap();
+        let cmu = note.cm(&JUBJUB);
+
+        // The sender has an outgoing viewing key, but chooses not to use it.
+        let mut ovk = OutgoingViewingKey([0; 32]);
+        rng.fill_bytes(&mut ovk.0);
+
+        let ne = SaplingNoteEncryption::new(None, note, pa, Memo::default(), &JUBJUB);
+        let epk = ne.epk();
+        let enc_ciphertext = ne.encrypt_note_plaintext();
+        let out_ciphertext = ne.encrypt_outgoing_plaintext(&cv, &cmu);
+
+        assert!(try_sapling_note_decryption(&ivk, epk, &cmu, &enc_ciphertext, &JUBJUB).is_some());
+        assert_eq!(
+            try_sapling_output_recovery(
+                &ovk,
+                &cv,
+                &cmu,
+                &epk,
+                &enc_ciphertext,
+                &out_ciphertext,
+                &JUBJUB
+            ),
             None
         );
     }
@@ -1125,7 +1318,8 @@ mod tests {
                 &cmu,
                 &epk,
                 &enc_ciphertext,
-                &out_ciphertext
+                &out_ciphertext,
+                &JUBJUB
             ),
             None
         );
@@ -1144,7 +1338,8 @@ mod tests {
                 &Fr::random(&mut rng),
                 &epk,
                 &enc_ciphertext,
-                &out_ciphertext
+                &out_ciphertext,
+                &JUBJUB
             ),
             None
         );
@@ -1163,7 +1358,8 @@ mod tests {
                 &cmu,
                 &edwards::Point::<Bls12, _>::rand(&mut rng, &JUBJUB).mul_by_cofactor(&JUBJUB),
                 &enc_ciphertext,
-                &out_ciphertext
+                &out_ciphertext,
+                &JUBJUB
             ),
             None
         );
@@ -1178,7 +1374,15 @@ mod tests {
 
         enc_ciphertext[ENC_CIPHERTEXT_SIZE - 1] ^= 0xff;
         assert_eq!(
-            try_sapling_output_recovery(&ovk, &cv, &cmu, &epk, &enc_ciphertext, &out_ciphertext),
+            try_sapling_output_recovery(
+                &ovk,
+                &cv,
+                &cmu,
+                &epk,
+                &enc_ciphertext,
+                &out_ciphertext,
+                &JUBJUB
+            ),
             None
         );
     }
@@ -1192,7 +1396,15 @@ mod tests {
 
         out_ciphertext[OUT_CIPHERTEXT_SIZE - 1] ^= 0xff;
         assert_eq!(
-            try_sapling_output_recovery(&ovk, &cv, &cmu, &epk, &enc_ciphertext, &out_ciphertext),
+            try_sapling_output_recovery(
+                &ovk,
+                &cv,
+                &cmu,
+                &epk,
+                &enc_ciphertext,
+                &out_ciphertext,
+                &JUBJUB
+            ),
             None
         );
     }
@@ -1214,7 +1426,15 @@ mod tests {
             |pt| pt[0] = 0x02,
         );
         assert_eq!(
-            try_sapling_output_recovery(&ovk, &cv, &cmu, &epk, &enc_ciphertext, &out_ciphertext),
+            try_sapling_output_recovery(
+                &ovk,
+                &cv,
+                &cmu,
+                &epk,
+                &enc_ciphertext,
+                &out_ciphertext,
+                &JUBJUB
+            ),
             None
         );
     }
@@ -1236,7 +1456,15 @@ mod tests {
             |pt| pt[1..12].copy_from_slice(&find_invalid_diversifier().0),
         );
         assert_eq!(
-            try_sapling_output_recovery(&ovk, &cv, &cmu, &epk, &enc_ciphertext, &out_ciphertext),
+            try_sapling_output_recovery(
+                &ovk,
+                &cv,
+                &cmu,
+                &epk,
+                &enc_ciphertext,
+                &out_ciphertext,
+                &JUBJUB
+            ),
             None
         );
     }
@@ -1258,7 +1486,15 @@ mod tests {
             |pt| pt[1..12].copy_from_slice(&find_valid_diversifier().0),
         );
         assert_eq!(
-            try_sapling_output_recovery(&ovk, &cv, &cmu, &epk, &enc_ciphertext, &out_ciphertext),
+            try_sapling_output_recovery(
+                &ovk,
+                &cv,
+                &cmu,
+                &epk,
+                &enc_ciphertext,
+                &out_ciphertext,
+                &JUBJUB
+            ),
             None
         );
     }
@@ -1308,7 +1544,7 @@ mod tests {
             // Test the individual components
             //
 
-            let shared_secret = sapling_ka_agree(&esk, &pk_d);
+            let shared_secret = sapling_ka_agree(&esk, &pk_d, &JUBJUB);
             {
                 let mut encoded = [0; 32];
                 shared_secret
@@ -1336,7 +1572,7 @@ mod tests {
             // (Tested first because it only requires immutable references.)
             //
 
-            match try_sapling_note_decryption(&ivk, &epk, &cmu, &tv.c_enc) {
+            match try_sapling_note_decryption(&ivk, &epk, &cmu, &tv.c_enc, &JUBJUB) {
                 Some((decrypted_note, decrypted_to, decrypted_memo)) => {
                     assert_eq!(decrypted_note, note);
                     assert_eq!(decrypted_to, to);
@@ -1350,6 +1586,7 @@ mod tests {
                 &epk,
                 &cmu,
                 &tv.c_enc[..COMPACT_NOTE_SIZE],
+                &JUBJUB,
             ) {
                 Some((decrypted_note, decrypted_to)) => {
                     assert_eq!(decrypted_note, note);
@@ -1358,7 +1595,8 @@ mod tests {
                 None => panic!("Compact note decryption failed"),
             }
 
-            match try_sapling_output_recovery(&ovk, &cv, &cmu, &epk, &tv.c_enc, &tv.c_out) {
+            match try_sapling_output_recovery(&ovk, &cv, &cmu, &epk, &tv.c_enc, &tv.c_out, &JUBJUB)
+            {
                 Some((decrypted_note, decrypted_to, decrypted_memo)) => {
                     assert_eq!(decrypted_note, note);
                     assert_eq!(decrypted_to, to);
@@ -1371,10 +1609,16 @@ mod tests {
             // Test encryption
             //
 
-            let mut ne = SaplingNoteEncryption::new(ovk, note, to, Memo(tv.memo));
-            // Swap in the ephemeral keypair from the test vectors
-            ne.esk = esk;
-            ne.epk = epk;
+            let ne = SaplingNoteEncryption::with_esk(
+                esk,
+                Some(ovk),
+                note,
+                to,
+                Memo(tv.memo),
+                &JUBJUB,
+                &mut OsRng,
+            );
+            assert_eq!(ne.epk(), &epk);
 
             assert_eq!(&ne.encrypt_note_plaintext()[..], &tv.c_enc[..]);
             assert_eq!(&ne.encrypt_outgoing_plaintext(&cv, &cmu)[..], &tv.c_out[..]);
@@ -1,17 +1,19 @@
 //! Structs and constants specific to the Sapling shielded pool.
 
-use ff::{BitIterator, PrimeField, PrimeFieldRepr};
+use ff::{BitIterator, Field, PrimeField, PrimeFieldRepr};
 use pairing::bls12_381::{Bls12, Fr, FrRepr};
+use rand_core::RngCore;
 use rand_os::OsRng;
 use sapling_crypto::{
     jubjub::{fs::Fs, FixedGenerators, JubjubBls12},
-    pedersen_hash::{pedersen_hash, Personalization},
-    primitives::Note,
+    pedersen_hash::{PedersenHasher, Personalization},
+    primitives::{Diversifier, Note, PaymentAddress},
     redjubjub::{PrivateKey, PublicKey, Signature},
 };
 use std::io::{self, Read, Write};
 
-use crate::merkle_tree::Hashable;
+use crate::keys::{prf_expand_vec, ExpandedSpendingKey, FullViewingKey};
+use crate::merkle_tree::{CommitmentTree, CommitmentTreeWitness, Hashable, IncrementalWitness};
 use JUBJUB;
 
 pub(crate) const SAPLING_COMMITMENT_TREE_DEPTH: usize =
@@ -19,6 +21,39 @@ pub(crate) const SAPLING_COMMITMENT_TREE_DEPTH: usize =
 
 /// Compute a parent node in the Sapling commitment tree given its two children.
 pub fn merkle_hash(depth: usize, lhs: &FrRepr, rhs: &FrRepr) -> FrRepr {
+    let bits = merkle_hash_bits(lhs, rhs);
+
+    match MERKLE_HASHERS.get(depth) {
+        Some(hasher) => hasher.hash(bits),
+        None => {
+            PedersenHasher::<Bls12>::new(Personalization::MerkleTree(depth), &JUBJUB).hash(bits)
+        }
+    }
+    .into_xy()
+    .0
+    .into_repr()
+}
+
+/// Compute the parent nodes in the Sapling commitment tree of each of the given pairs
+/// of children at the same depth.
+///
+/// This is equivalent to calling [`merkle_hash`] on each pair, but normalizes all of
+/// the hashes with a single field inversion.
+pub fn merkle_hash_many(depth: usize, pairs: &[(FrRepr, FrRepr)]) -> Vec<FrRepr> {
+    let inputs = pairs.iter().map(|(lhs, rhs)| merkle_hash_bits(lhs, rhs));
+
+    match MERKLE_HASHERS.get(depth) {
+        Some(hasher) => hasher.hash_many(inputs),
+        None => PedersenHasher::<Bls12>::new(Personalization::MerkleTree(depth), &JUBJUB)
+            .hash_many(inputs),
+    }
+    .into_iter()
+    .map(|(x, _)| x.into_repr())
+    .collect()
+}
+
+/// Returns the Pedersen hash input for the parent of the two given children.
+fn merkle_hash_bits(lhs: &FrRepr, rhs: &FrRepr) -> Vec<bool> {
     let lhs = {
         let mut tmp = [false; 256];
         for (a, b) in tmp.iter_mut().rev().zip(BitIterator::new(lhs)) {
@@ -35,17 +70,20 @@ pub fn merkle_hash(depth: usize, lhs: &FrRepr, rhs: &FrRepr) -> FrRepr {
         tmp
     };
 
-    pedersen_hash::<Bls12, _>(
-        Personalization::MerkleTree(depth),
-        lhs.iter()
-            .map(|&x| x)
-            .take(Fr::NUM_BITS as usize)
-            .chain(rhs.iter().map(|&x| x).take(Fr::NUM_BITS as usize)),
-        &JUBJUB,
-    )
-    .into_xy()
-    .0
-    .into_repr()
+    lhs.iter()
+        .map(|&x| x)
+        .take(Fr::NUM_BITS as usize)
+        .chain(rhs.iter().map(|&x| x).take(Fr::NUM_BITS as usize))
+        .collect()
+}
+
+lazy_static! {
+    /// Pedersen hashers for each depth of the Sapling commitment tree.
+    static ref MERKLE_HASHERS: Vec<PedersenHasher<'static, Bls12>> = {
+        (0..SAPLING_COMMITMENT_TREE_DEPTH)
+            .map(|depth| PedersenHasher::new(Personalization::MerkleTree(depth), &*JUBJUB))
+            .collect()
+    };
 }
 
 /// A node within the Sapling commitment tree.
@@ -77,6 +115,17 @@ impl Hashable for Node {
         }
     }
 
+    fn combine_many(depth: usize, pairs: &[(Self, Self)]) -> Vec<Self> {
+        let pairs: Vec<_> = pairs
+            .iter()
+            .map(|(lhs, rhs)| (lhs.repr, rhs.repr))
+            .collect();
+        merkle_hash_many(depth, &pairs)
+            .into_iter()
+            .map(Node::new)
+            .collect()
+    }
+
     fn blank() -> Self {
         Node {
             repr: Note::<Bls12>::uncommitted().into_repr(),
@@ -105,6 +154,44 @@ lazy_static! {
     };
 }
 
+/// Computes the commitment to a Sapling note with the given recipient, value, and
+/// commitment randomness.
+///
+/// Returns the u-coordinate of the commitment, which is the `cmu` field of an
+/// `OutputDescription` and the leaf added to the note commitment tree, or `None` if the
+/// recipient's diversifier is invalid.
+pub fn compute_note_commitment(
+    to: &PaymentAddress<Bls12>,
+    value: u64,
+    rcm: Fs,
+    params: &JubjubBls12,
+) -> Option<Fr> {
+    to.create_note(value, rcm, params)
+        .map(|note| note.cm(params))
+}
+
+/// Computes the nullifier that reveals the spend of a Sapling note with the given
+/// recipient, value, and commitment randomness, at the given position in the note
+/// commitment tree.
+///
+/// The nullifier depends on the full note commitment, not just its u-coordinate, so it
+/// is computed from the note's contents. Returns `None` if the recipient's diversifier is
+/// invalid.
+pub fn compute_nullifier(
+    fvk: &FullViewingKey<Bls12>,
+    to: &PaymentAddress<Bls12>,
+    value: u64,
+    rcm: Fs,
+    position: u64,
+    params: &JubjubBls12,
+) -> Option<[u8; 32]> {
+    to.create_note(value, rcm, params).map(|note| {
+        let mut nf = [0; 32];
+        nf.copy_from_slice(&note.nf(&fvk.vk, position, params));
+        nf
+    })
+}
+
 /// Create the spendAuthSig for a Sapling SpendDescription.
 pub fn spend_sig(
     ask: PrivateKey<Bls12>,
@@ -135,3 +222,294 @@ pub fn spend_sig(
         params,
     )
 }
+
+/// Returns the default diversifier for the given Sapling spending key.
+///
+/// This is the diversifier of the default address that zcashd derives for a
+/// non-ZIP 32 spending key: the first valid diversifier among the truncated outputs
+/// of `PRF^expand(sk, [3, i])` for `i = 0, 1, ...`.
+fn default_diversifier(sk: &[u8], params: &JubjubBls12) -> Diversifier {
+    (0..=255)
+        .map(|i| {
+            let mut d = [0; 11];
+            d.copy_from_slice(&prf_expand_vec(sk, &[&[3], &[i]]).as_bytes()[..11]);
+            Diversifier(d)
+        })
+        .find(|d| d.g_d::<Bls12>(params).is_some())
+        .expect("roughly half of all diversifiers are valid")
+}
+
+/// The private inputs to a Sapling spend of a zero-valued note under a random key.
+///
+/// Dummy spends can be added to a transaction to hide how many notes it really spends.
+/// The Sapling spend circuit only checks a note's Merkle path against the anchor when
+/// the note has a non-zero value, so the witness is to a tree containing only the dummy
+/// note, and the spend can use the same anchor as the transaction's real spends.
+///
+/// As in zcashd, the dummy note is sent to the default address of the random key.
+pub struct DummySpend {
+    pub expsk: ExpandedSpendingKey<Bls12>,
+    pub diversifier: Diversifier,
+    pub note: Note<Bls12>,
+    pub alpha: Fs,
+    pub witness: CommitmentTreeWitness<Node>,
+}
+
+impl DummySpend {
+    /// Generates a dummy spend from the given source of randomness.
+    ///
+    /// The result is a deterministic function of the output of `rng`.
+    pub fn generate<R: RngCore>(rng: &mut R, params: &JubjubBls12) -> Self {
+        let mut sk = [0; 32];
+        rng.fill_bytes(&mut sk);
+        let expsk = ExpandedSpendingKey::<Bls12>::from_spending_key(&sk);
+        let vk = expsk.proof_generation_key(params).into_viewing_key(params);
+
+        let diversifier = default_diversifier(&sk, params);
+        let to = vk
+            .into_payment_address(diversifier, params)
+            .expect("diversifier is valid");
+
+        let note = to
+            .create_note(0, Fs::random(rng), params)
+            .expect("diversifier is valid");
+        let alpha = Fs::random(rng);
+
+        let mut tree = CommitmentTree::new();
+        tree.append(Node::new(note.cm(params).into_repr()))
+            .expect("tree is empty");
+        let witness = IncrementalWitness::from_tree(&tree)
+            .path()
+            .expect("tree is not empty");
+
+        DummySpend {
+            expsk,
+            diversifier,
+            note,
+            alpha,
+            witness,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::{Field, PrimeField, PrimeFieldRepr};
+    use pairing::bls12_381::{Bls12, Fr};
+    use rand_core::{RngCore, SeedableRng};
+    use rand_os::OsRng;
+    use rand_xorshift::XorShiftRng;
+    use sapling_crypto::{
+        jubjub::{
+            fs::{Fs, FsRepr},
+            JubjubBls12, JubjubParams,
+        },
+        pedersen_hash::{pedersen_hash, Personalization},
+        primitives::Diversifier,
+    };
+
+    use super::{
+        compute_note_commitment, compute_nullifier, default_diversifier, merkle_hash,
+        merkle_hash_many, DummySpend, SAPLING_COMMITMENT_TREE_DEPTH,
+    };
+    use crate::{
+        keys::{ExpandedSpendingKey, FullViewingKey},
+        JUBJUB,
+    };
+
+    #[test]
+    fn note_commitments_and_nullifiers() {
+        struct TestVector {
+            sk: [u8; 32],
+            d: [u8; 11],
+            pk_d: [u8; 32],
+            value: u64,
+            rcm: [u8; 32],
+            cmu: [u8; 32],
+            position: u64,
+            nf: [u8; 32],
+        }
+
+        // From https://github.com/zcash-hackworks/zcash-test-vectors/blob/master/sapling_key_components.py
+        let test_vectors = vec![
+            TestVector {
+                sk: [
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                ],
+                d: [
+                    0xf1, 0x9d, 0x9b, 0x79, 0x7e, 0x39, 0xf3, 0x37, 0x44, 0x58, 0x39,
+                ],
+                pk_d: [
+                    0xdb, 0x4c, 0xd2, 0xb0, 0xaa, 0xc4, 0xf7, 0xeb, 0x8c, 0xa1, 0x31, 0xf1, 0x65,
+                    0x67, 0xc4, 0x45, 0xa9, 0x55, 0x51, 0x26, 0xd3, 0xc2, 0x9f, 0x14, 0xe3, 0xd7,
+                    0x76, 0xe8, 0x41, 0xae, 0x74, 0x15,
+                ],
+                value: 0,
+                rcm: [
+                    0x39, 0x17, 0x6d, 0xac, 0x39, 0xac, 0xe4, 0x98, 0x0e, 0xcc, 0x8d, 0x77, 0x8e,
+                    0x89, 0x86, 0x02, 0x55, 0xec, 0x36, 0x15, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00,
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                ],
+                cmu: [
+                    0xcb, 0x3c, 0xf9, 0x15, 0x32, 0x70, 0xd5, 0x7e, 0xb9, 0x14, 0xc6, 0xc2, 0xbc,
+                    0xc0, 0x18, 0x50, 0xc9, 0xfe, 0xd4, 0x4f, 0xce, 0x08, 0x06, 0x27, 0x8f, 0x08,
+                    0x3e, 0xf2, 0xdd, 0x07, 0x64, 0x39,
+                ],
+                position: 0,
+                nf: [
+                    0x44, 0xfa, 0xd6, 0x56, 0x4f, 0xfd, 0xec, 0x9f, 0xa1, 0x9c, 0x43, 0xa2, 0x8f,
+                    0x86, 0x1d, 0x5e, 0xbf, 0x60, 0x23, 0x46, 0x00, 0x7d, 0xe7, 0x62, 0x67, 0xd9,
+                    0x75, 0x27, 0x47, 0xab, 0x40, 0x63,
+                ],
+            },
+            TestVector {
+                sk: [
+                    0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+                    0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+                    0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+                ],
+                d: [
+                    0xae, 0xf1, 0x80, 0xf6, 0xe3, 0x4e, 0x35, 0x4b, 0x88, 0x8f, 0x81,
+                ],
+                pk_d: [
+                    0xa6, 0xb1, 0x3e, 0xa3, 0x36, 0xdd, 0xb7, 0xa6, 0x7b, 0xb0, 0x9a, 0x0e, 0x68,
+                    0xe9, 0xd3, 0xcf, 0xb3, 0x92, 0x10, 0x83, 0x1e, 0xa3, 0xa2, 0x96, 0xba, 0x09,
+                    0xa9, 0x22, 0x06, 0x0f, 0xd3, 0x8b,
+                ],
+                value: 12227227834928555328,
+                rcm: [
+                    0x47, 0x8b, 0xa0, 0xee, 0x6e, 0x1a, 0x75, 0xb6, 0x00, 0x03, 0x6f, 0x26, 0xf1,
+                    0x8b, 0x70, 0x15, 0xab, 0x55, 0x6b, 0xed, 0xdf, 0x8b, 0x96, 0x02, 0x38, 0x86,
+                    0x9f, 0x89, 0xdd, 0x80, 0x4e, 0x06,
+                ],
+                cmu: [
+                    0xb5, 0x78, 0x93, 0x50, 0x0b, 0xfb, 0x85, 0xdf, 0x2e, 0x8b, 0x01, 0xac, 0x45,
+                    0x2f, 0x89, 0xe1, 0x0e, 0x26, 0x6b, 0xcf, 0xa3, 0x1c, 0x31, 0xb2, 0x9a, 0x53,
+                    0xae, 0x72, 0xca, 0xd4, 0x69, 0x50,
+                ],
+                position: 763714296,
+                nf: [
+                    0x67, 0x9e, 0xb0, 0xc3, 0xa7, 0x57, 0xe2, 0xae, 0x83, 0xcd, 0xb4, 0x2a, 0x1a,
+                    0xb2, 0x59, 0xd7, 0x83, 0x88, 0x31, 0x54, 0x19, 0xad, 0xc7, 0x1d, 0x2e, 0x37,
+                    0x63, 0x17, 0x4c, 0x2e, 0x9d, 0x93,
+                ],
+            },
+        ];
+
+        for tv in test_vectors {
+            // The test vectors use the default diversifier of each key.
+            assert_eq!(default_diversifier(&tv.sk, &JUBJUB), Diversifier(tv.d));
+
+            let fvk = FullViewingKey::<Bls12>::from_expanded_spending_key(
+                &ExpandedSpendingKey::from_spending_key(&tv.sk),
+                &JUBJUB,
+            );
+            let to = fvk
+                .vk
+                .into_payment_address(Diversifier(tv.d), &JUBJUB)
+                .unwrap();
+            let mut pk_d = [0; 32];
+            to.pk_d.write(&mut pk_d[..]).unwrap();
+            assert_eq!(pk_d, tv.pk_d);
+
+            let mut rcm = FsRepr::default();
+            rcm.read_le(&tv.rcm[..]).unwrap();
+            let rcm = Fs::from_repr(rcm).unwrap();
+
+            let mut cmu = [0; 32];
+            compute_note_commitment(&to, tv.value, rcm, &JUBJUB)
+                .unwrap()
+                .into_repr()
+                .write_le(&mut cmu[..])
+                .unwrap();
+            assert_eq!(cmu, tv.cmu);
+
+            assert_eq!(
+                compute_nullifier(&fvk, &to, tv.value, rcm, tv.position, &JUBJUB),
+                Some(tv.nf)
+            );
+        }
+    }
+
+    #[test]
+    fn dummy_spend() {
+        let seed = [
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ];
+        let dummy = DummySpend::generate(&mut XorShiftRng::from_seed(seed), &JUBJUB);
+
+        assert_eq!(dummy.note.value, 0);
+        assert_eq!(dummy.witness.position, 0);
+        assert_eq!(dummy.witness.auth_path.len(), SAPLING_COMMITMENT_TREE_DEPTH);
+
+        // The note is spendable by the dummy key
+        let vk = dummy
+            .expsk
+            .proof_generation_key(&JUBJUB)
+            .into_viewing_key(&JUBJUB);
+        let to = vk.into_payment_address(dummy.diversifier, &JUBJUB).unwrap();
+        assert!(to.pk_d == dummy.note.pk_d);
+        assert!(to.g_d(&JUBJUB).unwrap() == dummy.note.g_d);
+
+        // The dummy spend is a deterministic function of the RNG output.
+        let again = DummySpend::generate(&mut XorShiftRng::from_seed(seed), &JUBJUB);
+        assert_eq!(&again.expsk.to_bytes()[..], &dummy.expsk.to_bytes()[..]);
+        assert_eq!(again.diversifier, dummy.diversifier);
+        assert!(again.note == dummy.note);
+        assert_eq!(again.alpha, dummy.alpha);
+        assert_eq!(again.witness, dummy.witness);
+    }
+
+    #[test]
+    fn merkle_hash_many_matches_merkle_hash() {
+        let mut rng = OsRng;
+
+        for depth in &[0, 1, SAPLING_COMMITMENT_TREE_DEPTH - 1] {
+            for count in &[0, 1, 2, 7] {
+                let pairs: Vec<_> = (0..*count)
+                    .map(|_| {
+                        (
+                            Fr::random(&mut rng).into_repr(),
+                            Fr::random(&mut rng).into_repr(),
+                        )
+                    })
+                    .collect();
+
+                let expected: Vec<_> = pairs
+                    .iter()
+                    .map(|(lhs, rhs)| merkle_hash(*depth, lhs, rhs))
+                    .collect();
+                assert_eq!(merkle_hash_many(*depth, &pairs), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn pedersen_hash_window_sizes() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let full = &*JUBJUB;
+        let small = JubjubBls12::with_pedersen_hash_exp_window_size(4);
+        let on_demand = JubjubBls12::with_pedersen_hash_exp_window_size(0);
+
+        assert_eq!(full.pedersen_hash_exp_window_size(), 8);
+        assert_eq!(small.pedersen_hash_exp_window_size(), 4);
+        assert!(small.memory_usage() < full.memory_usage());
+        assert!(on_demand.memory_usage() < small.memory_usage());
+
+        for length in &[0, 1, 6, 189, 510, 700] {
+            let input: Vec<bool> = (0..*length).map(|_| rng.next_u32() % 2 != 0).collect();
+            let personalization = Personalization::MerkleTree(0);
+
+            let expected = pedersen_hash::<Bls12, _>(personalization, input.clone(), full);
+            assert!(pedersen_hash::<Bls12, _>(personalization, input.clone(), &small) == expected);
+            assert!(pedersen_hash::<Bls12, _>(personalization, input, &on_demand) == expected);
+        }
+    }
+}
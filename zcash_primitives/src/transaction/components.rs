@@ -1,4 +1,4 @@
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
 use ff::{PrimeField, PrimeFieldRepr};
 use pairing::bls12_381::{Bls12, Fr, FrRepr};
 use sapling_crypto::{
@@ -18,8 +18,8 @@ const PHGR_PROOF_SIZE: usize = (33 + 33 + 65 + 33 + 33 + 33 + 33 + 33);
 const ZC_NUM_JS_INPUTS: usize = 2;
 const ZC_NUM_JS_OUTPUTS: usize = 2;
 
-const COIN: i64 = 1_0000_0000;
-const MAX_MONEY: i64 = 21_000_000 * COIN;
+pub const COIN: i64 = 1_0000_0000;
+pub const MAX_MONEY: i64 = 21_000_000 * COIN;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Amount(pub i64);
@@ -70,6 +70,44 @@ impl Script {
     pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
         Vector::write(&mut writer, &self.0, |w, e| w.write_u8(*e))
     }
+
+    /// Returns true if this script only pushes data onto the stack, ignoring the first
+    /// `skip` bytes.
+    pub fn is_push_only(&self, skip: usize) -> bool {
+        const OP_PUSHDATA1: u8 = 0x4c;
+        const OP_PUSHDATA2: u8 = 0x4d;
+        const OP_PUSHDATA4: u8 = 0x4e;
+        const OP_16: u8 = 0x60;
+
+        let s = &self.0;
+        let mut i = skip;
+        while i < s.len() {
+            let opcode = s[i];
+            i += 1;
+            let len = match opcode {
+                n if n < OP_PUSHDATA1 => n as usize,
+                OP_PUSHDATA1 if i < s.len() => {
+                    i += 1;
+                    s[i - 1] as usize
+                }
+                OP_PUSHDATA2 if i + 2 <= s.len() => {
+                    i += 2;
+                    LittleEndian::read_u16(&s[i - 2..i]) as usize
+                }
+                OP_PUSHDATA4 if i + 4 <= s.len() => {
+                    i += 4;
+                    LittleEndian::read_u32(&s[i - 4..i]) as usize
+                }
+                n if n > OP_PUSHDATA4 && n <= OP_16 => 0,
+                _ => return false,
+            };
+            if s.len() - i < len {
+                return false;
+            }
+            i += len;
+        }
+        true
+    }
 }
 
 #[derive(Debug)]
@@ -95,7 +133,7 @@ impl OutPoint {
 #[derive(Debug)]
 pub struct TxIn {
     pub prevout: OutPoint,
-    script_sig: Script,
+    pub script_sig: Script,
     pub sequence: u32,
 }
 
@@ -121,8 +159,8 @@ impl TxIn {
 
 #[derive(Debug)]
 pub struct TxOut {
-    value: Amount,
-    script_pubkey: Script,
+    pub value: Amount,
+    pub script_pubkey: Script,
 }
 
 impl TxOut {
@@ -310,10 +348,10 @@ impl std::fmt::Debug for SproutProof {
 }
 
 pub struct JSDescription {
-    vpub_old: Amount,
-    vpub_new: Amount,
+    pub vpub_old: Amount,
+    pub vpub_new: Amount,
     anchor: [u8; 32],
-    nullifiers: [[u8; 32]; ZC_NUM_JS_INPUTS],
+    pub nullifiers: [[u8; 32]; ZC_NUM_JS_INPUTS],
     commitments: [[u8; 32]; ZC_NUM_JS_OUTPUTS],
     ephemeral_key: [u8; 32],
     random_seed: [u8; 32],
@@ -449,7 +487,7 @@ impl JSDescription {
 
 #[cfg(test)]
 mod tests {
-    use super::{Amount, MAX_MONEY};
+    use super::{Amount, Script, MAX_MONEY};
 
     #[test]
     fn amount_in_range() {
@@ -492,4 +530,16 @@ mod tests {
         assert!(Amount::read_i64(&neg_max_money_m1[..], false).is_err());
         assert!(Amount::read_i64(&neg_max_money_m1[..], true).is_err());
     }
+
+    #[test]
+    fn push_only() {
+        assert!(Script(vec![]).is_push_only(0));
+        assert!(Script(vec![0x02, 0xab, 0xcd, 0x00, 0x51]).is_push_only(0));
+        assert!(Script(vec![0x4c, 0x01, 0xab]).is_push_only(0));
+        assert!(Script(vec![0x4d, 0x01, 0x00, 0xab]).is_push_only(0));
+        assert!(!Script(vec![0x02, 0xab]).is_push_only(0));
+        assert!(!Script(vec![0x4c]).is_push_only(0));
+        assert!(!Script(vec![0x76]).is_push_only(0));
+        assert!(Script(vec![0x6a, 0x00]).is_push_only(1));
+    }
 }
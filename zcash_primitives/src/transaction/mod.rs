@@ -10,19 +10,49 @@ use serialize::Vector;
 
 pub mod components;
 mod sighash;
+pub mod policy;
+pub mod size;
+pub mod summary;
+#[cfg(feature = "zfuture")]
+pub mod tze;
 
 #[cfg(test)]
 mod tests;
 
-pub use self::sighash::{signature_hash, signature_hash_data, SIGHASH_ALL};
+pub use self::sighash::{
+    joinsplits_hash, outputs_hash, prevout_hash, sequence_hash, shielded_outputs_hash,
+    shielded_spends_hash, signature_hash, signature_hash_data, single_output_hash, SIGHASH_ALL,
+    SIGHASH_ANYONECANPAY, SIGHASH_NONE, SIGHASH_SINGLE,
+};
+#[cfg(feature = "zfuture")]
+pub use self::sighash::{tze_inputs_hash, tze_outputs_hash};
 
 use self::components::{Amount, JSDescription, OutputDescription, SpendDescription, TxIn, TxOut};
+#[cfg(feature = "zfuture")]
+use self::tze::{TzeIn, TzeOut};
 
 const OVERWINTER_VERSION_GROUP_ID: u32 = 0x03C48270;
 const OVERWINTER_TX_VERSION: u32 = 3;
 const SAPLING_VERSION_GROUP_ID: u32 = 0x892F2085;
 const SAPLING_TX_VERSION: u32 = 4;
 
+/// The version group ID and version of the experimental `ZFUTURE` transaction format,
+/// which extends the Sapling format with TZE inputs and outputs.
+#[cfg(feature = "zfuture")]
+const ZFUTURE_VERSION_GROUP_ID: u32 = 0xFFFFFFFF;
+#[cfg(feature = "zfuture")]
+const ZFUTURE_TX_VERSION: u32 = 0x0000FFFF;
+
+#[cfg(feature = "zfuture")]
+fn is_zfuture(overwintered: bool, version_group_id: u32, version: u32) -> bool {
+    overwintered && version_group_id == ZFUTURE_VERSION_GROUP_ID && version == ZFUTURE_TX_VERSION
+}
+
+#[cfg(not(feature = "zfuture"))]
+fn is_zfuture(_: bool, _: u32, _: u32) -> bool {
+    false
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct TxId(pub [u8; 32]);
 
@@ -55,6 +85,10 @@ pub struct TransactionData {
     pub version_group_id: u32,
     pub vin: Vec<TxIn>,
     pub vout: Vec<TxOut>,
+    #[cfg(feature = "zfuture")]
+    pub tze_inputs: Vec<TzeIn>,
+    #[cfg(feature = "zfuture")]
+    pub tze_outputs: Vec<TzeOut>,
     pub lock_time: u32,
     pub expiry_height: u32,
     pub value_balance: Amount,
@@ -109,6 +143,10 @@ impl TransactionData {
             version_group_id: SAPLING_VERSION_GROUP_ID,
             vin: vec![],
             vout: vec![],
+            #[cfg(feature = "zfuture")]
+            tze_inputs: vec![],
+            #[cfg(feature = "zfuture")]
+            tze_outputs: vec![],
             lock_time: 0,
             expiry_height: 0,
             value_balance: Amount(0),
@@ -152,6 +190,11 @@ impl Transaction {
         self.txid
     }
 
+    /// Returns a structured summary of this transaction, suitable for debugging.
+    pub fn describe(&self) -> summary::TransactionSummary {
+        summary::TransactionSummary::from_tx(self)
+    }
+
     pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
         let header = reader.read_u32::<LittleEndian>()?;
         let overwintered = (header >> 31) == 1;
@@ -168,7 +211,9 @@ impl Transaction {
         let is_sapling_v4 = overwintered
             && version_group_id == SAPLING_VERSION_GROUP_ID
             && version == SAPLING_TX_VERSION;
-        if overwintered && !(is_overwinter_v3 || is_sapling_v4) {
+        let is_zfuture = is_zfuture(overwintered, version_group_id, version);
+        let has_sapling = is_sapling_v4 || is_zfuture;
+        if overwintered && !(is_overwinter_v3 || has_sapling) {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "Unknown transaction format",
@@ -177,13 +222,21 @@ impl Transaction {
 
         let vin = Vector::read(&mut reader, TxIn::read)?;
         let vout = Vector::read(&mut reader, TxOut::read)?;
+        #[cfg(feature = "zfuture")]
+        let (tze_inputs, tze_outputs) = if is_zfuture {
+            let tze_inputs = Vector::read(&mut reader, TzeIn::read)?;
+            let tze_outputs = Vector::read(&mut reader, TzeOut::read)?;
+            (tze_inputs, tze_outputs)
+        } else {
+            (vec![], vec![])
+        };
         let lock_time = reader.read_u32::<LittleEndian>()?;
-        let expiry_height = match is_overwinter_v3 || is_sapling_v4 {
+        let expiry_height = match is_overwinter_v3 || has_sapling {
             true => reader.read_u32::<LittleEndian>()?,
             false => 0,
         };
 
-        let (value_balance, shielded_spends, shielded_outputs) = if is_sapling_v4 {
+        let (value_balance, shielded_spends, shielded_outputs) = if has_sapling {
             let vb = Amount::read_i64(&mut reader, true)?;
             let ss = Vector::read(&mut reader, SpendDescription::read)?;
             let so = Vector::read(&mut reader, OutputDescription::read)?;
@@ -211,7 +264,7 @@ impl Transaction {
         };
 
         let binding_sig =
-            match is_sapling_v4 && !(shielded_spends.is_empty() && shielded_outputs.is_empty()) {
+            match has_sapling && !(shielded_spends.is_empty() && shielded_outputs.is_empty()) {
                 true => Some(Signature::read(&mut reader)?),
                 false => None,
             };
@@ -222,6 +275,10 @@ impl Transaction {
             version_group_id,
             vin,
             vout,
+            #[cfg(feature = "zfuture")]
+            tze_inputs,
+            #[cfg(feature = "zfuture")]
+            tze_outputs,
             lock_time,
             expiry_height,
             value_balance,
@@ -246,7 +303,9 @@ impl Transaction {
         let is_sapling_v4 = self.overwintered
             && self.version_group_id == SAPLING_VERSION_GROUP_ID
             && self.version == SAPLING_TX_VERSION;
-        if self.overwintered && !(is_overwinter_v3 || is_sapling_v4) {
+        let is_zfuture = is_zfuture(self.overwintered, self.version_group_id, self.version);
+        let has_sapling = is_sapling_v4 || is_zfuture;
+        if self.overwintered && !(is_overwinter_v3 || has_sapling) {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "Unknown transaction format",
@@ -255,12 +314,24 @@ impl Transaction {
 
         Vector::write(&mut writer, &self.vin, |w, e| e.write(w))?;
         Vector::write(&mut writer, &self.vout, |w, e| e.write(w))?;
+        #[cfg(feature = "zfuture")]
+        {
+            if is_zfuture {
+                Vector::write(&mut writer, &self.tze_inputs, |w, e| e.write(w))?;
+                Vector::write(&mut writer, &self.tze_outputs, |w, e| e.write(w))?;
+            } else if !(self.tze_inputs.is_empty() && self.tze_outputs.is_empty()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "TZE inputs and outputs should not be present",
+                ));
+            }
+        }
         writer.write_u32::<LittleEndian>(self.lock_time)?;
-        if is_overwinter_v3 || is_sapling_v4 {
+        if is_overwinter_v3 || has_sapling {
             writer.write_u32::<LittleEndian>(self.expiry_height)?;
         }
 
-        if is_sapling_v4 {
+        if has_sapling {
             writer.write_i64::<LittleEndian>(self.value_balance.0)?;
             Vector::write(&mut writer, &self.shielded_spends, |w, e| e.write(w))?;
             Vector::write(&mut writer, &self.shielded_outputs, |w, e| e.write(w))?;
@@ -305,7 +376,7 @@ impl Transaction {
             }
         }
 
-        if is_sapling_v4 && !(self.shielded_spends.is_empty() && self.shielded_outputs.is_empty()) {
+        if has_sapling && !(self.shielded_spends.is_empty() && self.shielded_outputs.is_empty()) {
             match self.binding_sig {
                 Some(sig) => sig.write(&mut writer)?,
                 None => {
@@ -0,0 +1,404 @@
+//! Checks for the standardness rules that zcashd applies when accepting transactions
+//! into its mempool.
+//!
+//! These rules are relay policy rather than consensus rules, so a transaction that
+//! fails them may still be valid in a block, but will not be relayed by default.
+
+use std::error;
+use std::fmt;
+
+use crate::consensus::{NetworkUpgrade, UpgradeHeights};
+
+use super::{
+    components::Amount,
+    summary::{ScriptType, TxFormat},
+    Transaction,
+};
+
+/// The maximum size of a standard transaction, in bytes.
+pub const MAX_STANDARD_TX_SIZE: usize = 100_000;
+
+/// The maximum size of a standard transparent input's `scriptSig`, in bytes.
+pub const MAX_STANDARD_SCRIPTSIG_SIZE: usize = 1650;
+
+/// The maximum number of public keys in a standard bare multisig output script.
+pub const MAX_STANDARD_MULTISIG_KEYS: u8 = 3;
+
+/// The maximum size of a standard `OP_RETURN` output script, in bytes.
+pub const MAX_OP_RETURN_RELAY: usize = 223;
+
+/// The number of blocks before its expiry height at which a transaction is no longer
+/// accepted into the mempool.
+pub const TX_EXPIRING_SOON_THRESHOLD: u32 = 3;
+
+/// zcashd's default minimum relay fee rate, in zatoshis per 1000 bytes.
+const MIN_RELAY_FEE_RATE: i64 = 100;
+
+/// The size of a P2PKH input that would spend an output, for the dust calculation.
+const SPENDING_INPUT_SIZE: usize = 148;
+
+fn min_relay_fee(size: usize) -> i64 {
+    MIN_RELAY_FEE_RATE * size as i64 / 1000
+}
+
+/// A reason for which zcashd would not accept a transaction into its mempool.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NonStandard {
+    /// The transaction format is not one that zcashd relays at this height.
+    Version(TxFormat),
+    /// The transaction is larger than [`MAX_STANDARD_TX_SIZE`].
+    TxSize(usize),
+    /// The `scriptSig` of the given transparent input is too large.
+    ScriptSigSize(usize),
+    /// The `scriptSig` of the given transparent input does not only push data.
+    ScriptSigNotPushOnly(usize),
+    /// The `scriptPubKey` of the given transparent output is not a standard script.
+    ScriptPubKey(usize),
+    /// The given transparent output is too small to be worth spending.
+    Dust(usize),
+    /// The transaction has more than one `OP_RETURN` output.
+    MultiOpReturn,
+    /// The total value of the transparent outputs or of the JoinSplit public values is
+    /// out of range.
+    ValueOutOfRange,
+    /// The transaction expired before the given height.
+    Expired,
+    /// The transaction expires within [`TX_EXPIRING_SOON_THRESHOLD`] blocks.
+    ExpiringSoon,
+    /// The transaction pays the given fee, which zcashd considers absurdly high.
+    AbsurdFee(Amount),
+}
+
+impl fmt::Display for NonStandard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NonStandard::Version(format) => write!(f, "Non-standard transaction format {}", format),
+            NonStandard::TxSize(size) => write!(
+                f,
+                "Transaction size {} exceeds the standard limit of {} bytes",
+                size, MAX_STANDARD_TX_SIZE
+            ),
+            NonStandard::ScriptSigSize(i) => write!(f, "scriptSig of input {} is too large", i),
+            NonStandard::ScriptSigNotPushOnly(i) => {
+                write!(f, "scriptSig of input {} is not push-only", i)
+            }
+            NonStandard::ScriptPubKey(i) => {
+                write!(f, "scriptPubKey of output {} is non-standard", i)
+            }
+            NonStandard::Dust(i) => write!(f, "Output {} is dust", i),
+            NonStandard::MultiOpReturn => write!(f, "Transaction has multiple OP_RETURN outputs"),
+            NonStandard::ValueOutOfRange => write!(f, "Transaction values out of range"),
+            NonStandard::Expired => write!(f, "Transaction has expired"),
+            NonStandard::ExpiringSoon => write!(f, "Transaction is expiring soon"),
+            NonStandard::AbsurdFee(fee) => {
+                write!(f, "Transaction fee of {} zatoshis is absurdly high", fee.0)
+            }
+        }
+    }
+}
+
+impl error::Error for NonStandard {}
+
+/// Checks whether zcashd would accept the given transaction into its mempool, for
+/// inclusion in the block at `height` on the network with the given upgrades.
+///
+/// All detected issues are returned. The fee can only be checked for transactions
+/// without transparent inputs, because the values of those inputs are not recorded in
+/// the transaction.
+pub fn check_standard(
+    tx: &Transaction,
+    height: u32,
+    upgrades: &UpgradeHeights,
+) -> Result<(), Vec<NonStandard>> {
+    let mut issues = vec![];
+    let summary = tx.describe();
+
+    let overwinter_active = upgrades.is_active(NetworkUpgrade::Overwinter, height);
+    let sapling_active = upgrades.is_active(NetworkUpgrade::Sapling, height);
+    let standard_format = match summary.format {
+        TxFormat::Sprout(1) | TxFormat::Sprout(2) => !overwinter_active,
+        TxFormat::Overwinter => overwinter_active && !sapling_active,
+        TxFormat::Sapling => sapling_active,
+        _ => false,
+    };
+    if !standard_format {
+        issues.push(NonStandard::Version(summary.format));
+    }
+
+    let mut raw = vec![];
+    tx.write(&mut raw)
+        .expect("a parsed transaction can be serialized");
+    if raw.len() >= MAX_STANDARD_TX_SIZE {
+        issues.push(NonStandard::TxSize(raw.len()));
+    }
+
+    for (i, txin) in tx.vin.iter().enumerate() {
+        if txin.script_sig.0.len() > MAX_STANDARD_SCRIPTSIG_SIZE {
+            issues.push(NonStandard::ScriptSigSize(i));
+        }
+        if !txin.script_sig.is_push_only(0) {
+            issues.push(NonStandard::ScriptSigNotPushOnly(i));
+        }
+    }
+
+    let mut op_returns = 0;
+    for (i, txout) in tx.vout.iter().enumerate() {
+        let script = &txout.script_pubkey;
+        match ScriptType::of(script) {
+            ScriptType::NullData => {
+                op_returns += 1;
+                if script.0.len() > MAX_OP_RETURN_RELAY || !script.is_push_only(1) {
+                    issues.push(NonStandard::ScriptPubKey(i));
+                }
+            }
+            ScriptType::Nonstandard => issues.push(NonStandard::ScriptPubKey(i)),
+            ScriptType::Multisig { keys, .. } if keys > MAX_STANDARD_MULTISIG_KEYS => {
+                issues.push(NonStandard::ScriptPubKey(i))
+            }
+            ScriptType::P2PKH
+            | ScriptType::P2SH
+            | ScriptType::P2PK
+            | ScriptType::Multisig { .. } => {
+                let mut out = vec![];
+                txout.write(&mut out).unwrap();
+                if txout.value.0 < 3 * min_relay_fee(out.len() + SPENDING_INPUT_SIZE) {
+                    issues.push(NonStandard::Dust(i));
+                }
+            }
+        }
+    }
+    if op_returns > 1 {
+        issues.push(NonStandard::MultiOpReturn);
+    }
+
+    if summary.transparent_output_value().is_none()
+        || summary.sprout_value_in.is_none()
+        || summary.sprout_value_out.is_none()
+    {
+        issues.push(NonStandard::ValueOutOfRange);
+    }
+
+    if tx.expiry_height != 0 {
+        if height > tx.expiry_height {
+            issues.push(NonStandard::Expired);
+        } else if u64::from(height) + u64::from(TX_EXPIRING_SOON_THRESHOLD)
+            > u64::from(tx.expiry_height)
+        {
+            issues.push(NonStandard::ExpiringSoon);
+        }
+    }
+
+    // The fee is only out of range if the values checked above are.
+    if tx.vin.is_empty() {
+        if let Some(fee) = summary.fee(Amount(0)) {
+            if fee.0 > min_relay_fee(raw.len()) * 10000 {
+                issues.push(NonStandard::AbsurdFee(fee));
+            }
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_standard, NonStandard};
+    use crate::{
+        consensus::TEST_NETWORK_UPGRADES,
+        transaction::{
+            components::{Amount, Script, TxOut, MAX_MONEY},
+            summary::TxFormat,
+            TransactionData, OVERWINTER_TX_VERSION, OVERWINTER_VERSION_GROUP_ID,
+        },
+    };
+
+    const SAPLING_HEIGHT: u32 = 280_000;
+
+    fn p2pkh() -> Script {
+        let mut script = vec![0x76, 0xa9, 0x14];
+        script.extend_from_slice(&[0; 20]);
+        script.extend_from_slice(&[0x88, 0xac]);
+        Script(script)
+    }
+
+    #[test]
+    fn standard_tx() {
+        let mut tx = TransactionData::new();
+        tx.vout.push(TxOut {
+            value: Amount(10000),
+            script_pubkey: p2pkh(),
+        });
+        tx.vout.push(TxOut {
+            value: Amount(0),
+            script_pubkey: Script(vec![0x6a, 0x02, 0xab, 0xcd]),
+        });
+        tx.value_balance = Amount(20000);
+        tx.expiry_height = SAPLING_HEIGHT + 100;
+        let tx = tx.freeze().unwrap();
+
+        assert_eq!(
+            check_standard(&tx, SAPLING_HEIGHT + 97, &TEST_NETWORK_UPGRADES),
+            Ok(())
+        );
+        assert_eq!(
+            check_standard(&tx, SAPLING_HEIGHT + 98, &TEST_NETWORK_UPGRADES),
+            Err(vec![NonStandard::ExpiringSoon])
+        );
+        assert_eq!(
+            check_standard(&tx, SAPLING_HEIGHT + 101, &TEST_NETWORK_UPGRADES),
+            Err(vec![NonStandard::Expired])
+        );
+
+        let mut tx = TransactionData::new();
+        tx.expiry_height = u32::max_value();
+        let tx = tx.freeze().unwrap();
+        assert_eq!(
+            check_standard(&tx, u32::max_value() - 1, &TEST_NETWORK_UPGRADES),
+            Err(vec![NonStandard::ExpiringSoon])
+        );
+    }
+
+    #[test]
+    fn nonstandard_outputs() {
+        let mut tx = TransactionData::new();
+        tx.vout.push(TxOut {
+            value: Amount(53),
+            script_pubkey: p2pkh(),
+        });
+        tx.vout.push(TxOut {
+            value: Amount(1000),
+            script_pubkey: Script(vec![0x51]),
+        });
+        tx.vout.push(TxOut {
+            value: Amount(0),
+            script_pubkey: Script(vec![0x6a]),
+        });
+        tx.vout.push(TxOut {
+            value: Amount(0),
+            script_pubkey: Script(vec![0x6a, 0x51, 0x61]),
+        });
+        tx.value_balance = Amount(1053 + 1_000_000_000);
+        let tx = tx.freeze().unwrap();
+
+        assert_eq!(
+            check_standard(&tx, SAPLING_HEIGHT, &TEST_NETWORK_UPGRADES),
+            Err(vec![
+                NonStandard::Dust(0),
+                NonStandard::ScriptPubKey(1),
+                NonStandard::ScriptPubKey(3),
+                NonStandard::MultiOpReturn,
+                NonStandard::AbsurdFee(Amount(1_000_000_000)),
+            ])
+        );
+    }
+
+    #[test]
+    fn formats_by_height() {
+        let overwinter_height = TEST_NETWORK_UPGRADES.overwinter.unwrap();
+
+        let tx = TransactionData::new().freeze().unwrap();
+        assert_eq!(
+            check_standard(&tx, SAPLING_HEIGHT, &TEST_NETWORK_UPGRADES),
+            Ok(())
+        );
+        assert_eq!(
+            check_standard(&tx, SAPLING_HEIGHT - 1, &TEST_NETWORK_UPGRADES),
+            Err(vec![NonStandard::Version(TxFormat::Sapling)])
+        );
+
+        let mut tx = TransactionData::new();
+        tx.version = OVERWINTER_TX_VERSION;
+        tx.version_group_id = OVERWINTER_VERSION_GROUP_ID;
+        let tx = tx.freeze().unwrap();
+        assert_eq!(
+            check_standard(&tx, overwinter_height, &TEST_NETWORK_UPGRADES),
+            Ok(())
+        );
+        assert_eq!(
+            check_standard(&tx, SAPLING_HEIGHT, &TEST_NETWORK_UPGRADES),
+            Err(vec![NonStandard::Version(TxFormat::Overwinter)])
+        );
+
+        let mut tx = TransactionData::new();
+        tx.overwintered = false;
+        tx.version = 1;
+        tx.version_group_id = 0;
+        let tx = tx.freeze().unwrap();
+        assert_eq!(
+            check_standard(&tx, overwinter_height - 1, &TEST_NETWORK_UPGRADES),
+            Ok(())
+        );
+        assert_eq!(
+            check_standard(&tx, overwinter_height, &TEST_NETWORK_UPGRADES),
+            Err(vec![NonStandard::Version(TxFormat::Sprout(1))])
+        );
+    }
+
+    #[test]
+    fn values_out_of_range() {
+        let mut tx = TransactionData::new();
+        for _ in 0..2 {
+            tx.vout.push(TxOut {
+                value: Amount(MAX_MONEY),
+                script_pubkey: p2pkh(),
+            });
+        }
+        let tx = tx.freeze().unwrap();
+
+        assert_eq!(
+            check_standard(&tx, SAPLING_HEIGHT, &TEST_NETWORK_UPGRADES),
+            Err(vec![NonStandard::ValueOutOfRange])
+        );
+    }
+
+    #[test]
+    fn pubkey_outputs() {
+        let pubkey = || {
+            let mut push = vec![0x21];
+            push.extend_from_slice(&[2; 33]);
+            push
+        };
+
+        let mut p2pk = pubkey();
+        p2pk.push(0xac);
+
+        // OP_1 <pubkey> x n OP_n OP_CHECKMULTISIG
+        let multisig = |n: u8| {
+            let mut script = vec![0x51];
+            for _ in 0..n {
+                script.extend(pubkey());
+            }
+            script.extend_from_slice(&[0x50 + n, 0xae]);
+            Script(script)
+        };
+
+        let mut tx = TransactionData::new();
+        tx.vout.push(TxOut {
+            value: Amount(10000),
+            script_pubkey: Script(p2pk),
+        });
+        tx.vout.push(TxOut {
+            value: Amount(10000),
+            script_pubkey: multisig(3),
+        });
+        tx.vout.push(TxOut {
+            value: Amount(10000),
+            script_pubkey: multisig(4),
+        });
+        tx.vout.push(TxOut {
+            value: Amount(53),
+            script_pubkey: multisig(1),
+        });
+        tx.value_balance = Amount(40053);
+        let tx = tx.freeze().unwrap();
+
+        assert_eq!(
+            check_standard(&tx, SAPLING_HEIGHT, &TEST_NETWORK_UPGRADES),
+            Err(vec![NonStandard::ScriptPubKey(2), NonStandard::Dust(3)])
+        );
+    }
+}
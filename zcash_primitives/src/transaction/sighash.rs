@@ -2,25 +2,30 @@ use blake2b_simd::{Hash as Blake2bHash, Params as Blake2bParams};
 use byteorder::{LittleEndian, WriteBytesExt};
 use ff::{PrimeField, PrimeFieldRepr};
 
+#[cfg(feature = "zfuture")]
+use super::ZFUTURE_VERSION_GROUP_ID;
 use super::{
     components::{Amount, Script, TxOut},
     Transaction, TransactionData, OVERWINTER_VERSION_GROUP_ID, SAPLING_TX_VERSION,
     SAPLING_VERSION_GROUP_ID,
 };
-
-const ZCASH_SIGHASH_PERSONALIZATION_PREFIX: &'static [u8; 12] = b"ZcashSigHash";
-const ZCASH_PREVOUTS_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashPrevoutHash";
-const ZCASH_SEQUENCE_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashSequencHash";
-const ZCASH_OUTPUTS_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashOutputsHash";
-const ZCASH_JOINSPLITS_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashJSplitsHash";
-const ZCASH_SHIELDED_SPENDS_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashSSpendsHash";
-const ZCASH_SHIELDED_OUTPUTS_HASH_PERSONALIZATION: &'static [u8; 16] = b"ZcashSOutputHash";
+use crate::constants::{
+    ZCASH_JOINSPLITS_HASH_PERSONALIZATION, ZCASH_OUTPUTS_HASH_PERSONALIZATION,
+    ZCASH_PREVOUTS_HASH_PERSONALIZATION, ZCASH_SEQUENCE_HASH_PERSONALIZATION,
+    ZCASH_SHIELDED_OUTPUTS_HASH_PERSONALIZATION, ZCASH_SHIELDED_SPENDS_HASH_PERSONALIZATION,
+    ZCASH_SIGHASH_PERSONALIZATION_PREFIX,
+};
+#[cfg(feature = "zfuture")]
+use crate::{
+    constants::{ZCASH_TZE_INPUTS_HASH_PERSONALIZATION, ZCASH_TZE_OUTPUTS_HASH_PERSONALIZATION},
+    serialize::CompactSize,
+};
 
 pub const SIGHASH_ALL: u32 = 1;
-const SIGHASH_NONE: u32 = 2;
-const SIGHASH_SINGLE: u32 = 3;
+pub const SIGHASH_NONE: u32 = 2;
+pub const SIGHASH_SINGLE: u32 = 3;
 const SIGHASH_MASK: u32 = 0x1f;
-const SIGHASH_ANYONECANPAY: u32 = 0x80;
+pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
 
 macro_rules! update_u32 {
     ($h:expr, $value:expr, $tmp:expr) => {
@@ -51,6 +56,8 @@ enum SigHashVersion {
     Sprout,
     Overwinter,
     Sapling,
+    #[cfg(feature = "zfuture")]
+    ZFuture,
 }
 
 impl SigHashVersion {
@@ -59,15 +66,25 @@ impl SigHashVersion {
             match tx.version_group_id {
                 OVERWINTER_VERSION_GROUP_ID => SigHashVersion::Overwinter,
                 SAPLING_VERSION_GROUP_ID => SigHashVersion::Sapling,
+                #[cfg(feature = "zfuture")]
+                ZFUTURE_VERSION_GROUP_ID => SigHashVersion::ZFuture,
                 _ => unimplemented!(),
             }
         } else {
             SigHashVersion::Sprout
         }
     }
+
+    fn has_sapling_components(&self) -> bool {
+        match self {
+            SigHashVersion::Sprout | SigHashVersion::Overwinter => false,
+            _ => true,
+        }
+    }
 }
 
-fn prevout_hash(tx: &TransactionData) -> Blake2bHash {
+/// Computes `hashPrevouts` for the given transaction, as defined in ZIP 143.
+pub fn prevout_hash(tx: &TransactionData) -> Blake2bHash {
     let mut data = Vec::with_capacity(tx.vin.len() * 36);
     for t_in in &tx.vin {
         t_in.prevout.write(&mut data).unwrap();
@@ -78,7 +95,8 @@ fn prevout_hash(tx: &TransactionData) -> Blake2bHash {
         .hash(&data)
 }
 
-fn sequence_hash(tx: &TransactionData) -> Blake2bHash {
+/// Computes `hashSequence` for the given transaction, as defined in ZIP 143.
+pub fn sequence_hash(tx: &TransactionData) -> Blake2bHash {
     let mut data = Vec::with_capacity(tx.vin.len() * 4);
     for t_in in &tx.vin {
         (&mut data)
@@ -91,7 +109,9 @@ fn sequence_hash(tx: &TransactionData) -> Blake2bHash {
         .hash(&data)
 }
 
-fn outputs_hash(tx: &TransactionData) -> Blake2bHash {
+/// Computes `hashOutputs` over all of the transparent outputs of the given
+/// transaction, as defined in ZIP 143.
+pub fn outputs_hash(tx: &TransactionData) -> Blake2bHash {
     let mut data = Vec::with_capacity(tx.vout.len() * (4 + 1));
     for t_out in &tx.vout {
         t_out.write(&mut data).unwrap();
@@ -102,7 +122,9 @@ fn outputs_hash(tx: &TransactionData) -> Blake2bHash {
         .hash(&data)
 }
 
-fn single_output_hash(tx_out: &TxOut) -> Blake2bHash {
+/// Computes `hashOutputs` over a single transparent output, as used with
+/// [`SIGHASH_SINGLE`].
+pub fn single_output_hash(tx_out: &TxOut) -> Blake2bHash {
     let mut data = vec![];
     tx_out.write(&mut data).unwrap();
     Blake2bParams::new()
@@ -111,7 +133,8 @@ fn single_output_hash(tx_out: &TxOut) -> Blake2bHash {
         .hash(&data)
 }
 
-fn joinsplits_hash(tx: &TransactionData) -> Blake2bHash {
+/// Computes `hashJoinSplits` for the given transaction, as defined in ZIP 143.
+pub fn joinsplits_hash(tx: &TransactionData) -> Blake2bHash {
     let mut data = Vec::with_capacity(
         tx.joinsplits.len()
             * if tx.version < SAPLING_TX_VERSION {
@@ -123,14 +146,17 @@ fn joinsplits_hash(tx: &TransactionData) -> Blake2bHash {
     for js in &tx.joinsplits {
         js.write(&mut data).unwrap();
     }
-    data.extend_from_slice(&tx.joinsplit_pubkey.unwrap());
+    if let Some(joinsplit_pubkey) = tx.joinsplit_pubkey {
+        data.extend_from_slice(&joinsplit_pubkey);
+    }
     Blake2bParams::new()
         .hash_length(32)
         .personal(ZCASH_JOINSPLITS_HASH_PERSONALIZATION)
         .hash(&data)
 }
 
-fn shielded_spends_hash(tx: &TransactionData) -> Blake2bHash {
+/// Computes `hashShieldedSpends` for the given transaction, as defined in ZIP 243.
+pub fn shielded_spends_hash(tx: &TransactionData) -> Blake2bHash {
     let mut data = Vec::with_capacity(tx.shielded_spends.len() * 384);
     for s_spend in &tx.shielded_spends {
         s_spend.cv.write(&mut data).unwrap();
@@ -145,7 +171,9 @@ fn shielded_spends_hash(tx: &TransactionData) -> Blake2bHash {
         .hash(&data)
 }
 
-fn shielded_outputs_hash(tx: &TransactionData) -> Blake2bHash {
+/// Computes `hashShieldedOutputs` for the given transaction, as defined in
+/// ZIP 243.
+pub fn shielded_outputs_hash(tx: &TransactionData) -> Blake2bHash {
     let mut data = Vec::with_capacity(tx.shielded_outputs.len() * 948);
     for s_out in &tx.shielded_outputs {
         s_out.write(&mut data).unwrap();
@@ -156,6 +184,37 @@ fn shielded_outputs_hash(tx: &TransactionData) -> Blake2bHash {
         .hash(&data)
 }
 
+/// Computes `hashTzeInputs` for the given ZFUTURE transaction.
+///
+/// Witness payloads are not committed to, so that a witness can depend on the
+/// signature hash.
+#[cfg(feature = "zfuture")]
+pub fn tze_inputs_hash(tx: &TransactionData) -> Blake2bHash {
+    let mut data = vec![];
+    for tze_in in &tx.tze_inputs {
+        tze_in.prevout.write(&mut data).unwrap();
+        CompactSize::write(&mut data, tze_in.witness.extension_id as usize).unwrap();
+        CompactSize::write(&mut data, tze_in.witness.mode as usize).unwrap();
+    }
+    Blake2bParams::new()
+        .hash_length(32)
+        .personal(ZCASH_TZE_INPUTS_HASH_PERSONALIZATION)
+        .hash(&data)
+}
+
+/// Computes `hashTzeOutputs` for the given ZFUTURE transaction.
+#[cfg(feature = "zfuture")]
+pub fn tze_outputs_hash(tx: &TransactionData) -> Blake2bHash {
+    let mut data = vec![];
+    for tze_out in &tx.tze_outputs {
+        tze_out.write(&mut data).unwrap();
+    }
+    Blake2bParams::new()
+        .hash_length(32)
+        .personal(ZCASH_TZE_OUTPUTS_HASH_PERSONALIZATION)
+        .hash(&data)
+}
+
 pub fn signature_hash_data(
     tx: &TransactionData,
     consensus_branch_id: u32,
@@ -164,7 +223,8 @@ pub fn signature_hash_data(
 ) -> Vec<u8> {
     let sigversion = SigHashVersion::from_tx(tx);
     match sigversion {
-        SigHashVersion::Overwinter | SigHashVersion::Sapling => {
+        SigHashVersion::Sprout => unimplemented!(),
+        _ => {
             let mut personal = [0; 16];
             (&mut personal[..12]).copy_from_slice(ZCASH_SIGHASH_PERSONALIZATION_PREFIX);
             (&mut personal[12..])
@@ -202,7 +262,7 @@ pub fn signature_hash_data(
                 h.update(&[0; 32]);
             };
             update_hash!(h, !tx.joinsplits.is_empty(), joinsplits_hash(tx));
-            if sigversion == SigHashVersion::Sapling {
+            if sigversion.has_sapling_components() {
                 update_hash!(h, !tx.shielded_spends.is_empty(), shielded_spends_hash(tx));
                 update_hash!(
                     h,
@@ -210,9 +270,16 @@ pub fn signature_hash_data(
                     shielded_outputs_hash(tx)
                 );
             }
+            #[cfg(feature = "zfuture")]
+            {
+                if sigversion == SigHashVersion::ZFuture {
+                    update_hash!(h, !tx.tze_inputs.is_empty(), tze_inputs_hash(tx));
+                    update_hash!(h, !tx.tze_outputs.is_empty(), tze_outputs_hash(tx));
+                }
+            }
             update_u32!(h, tx.lock_time, tmp);
             update_u32!(h, tx.expiry_height, tmp);
-            if sigversion == SigHashVersion::Sapling {
+            if sigversion.has_sapling_components() {
                 update_i64!(h, tx.value_balance.0, tmp);
             }
             update_u32!(h, hash_type, tmp);
@@ -230,7 +297,6 @@ pub fn signature_hash_data(
 
             h.finalize().as_ref().to_vec()
         }
-        SigHashVersion::Sprout => unimplemented!(),
     }
 }
 
@@ -0,0 +1,159 @@
+//! Size and logical action estimates for Sapling (v4) transactions.
+
+use super::Transaction;
+
+/// The maximum size of a transaction after Sapling activation, in bytes.
+pub const MAX_TX_SIZE: usize = 2_000_000;
+
+/// The size of a standard P2PKH transparent input, as used by ZIP 317.
+pub const P2PKH_STANDARD_INPUT_SIZE: usize = 150;
+
+/// The size of a standard P2PKH transparent output, as used by ZIP 317.
+pub const P2PKH_STANDARD_OUTPUT_SIZE: usize = 34;
+
+// header + nVersionGroupId + nLockTime + nExpiryHeight + valueBalance
+const V4_FIXED_SIZE: usize = 4 + 4 + 4 + 4 + 8;
+// cv + anchor + nullifier + rk + zkproof + spendAuthSig
+const SPEND_DESCRIPTION_SIZE: usize = 32 + 32 + 32 + 32 + 192 + 64;
+// cv + cmu + ephemeralKey + encCiphertext + outCiphertext + zkproof
+const OUTPUT_DESCRIPTION_SIZE: usize = 32 + 32 + 32 + 580 + 80 + 192;
+const BINDING_SIG_SIZE: usize = 64;
+
+fn compact_size_len(n: usize) -> usize {
+    match n {
+        n if n < 253 => 1,
+        n if n <= 0xFFFF => 3,
+        n if n <= 0xFFFFFFFF => 5,
+        _ => 9,
+    }
+}
+
+fn ceil_div(a: usize, b: usize) -> usize {
+    (a + b - 1) / b
+}
+
+/// Estimates the size in bytes of a Sapling (v4) transaction with the given numbers of
+/// Sapling spends and outputs, and of standard P2PKH transparent inputs and outputs.
+///
+/// Transparent inputs are assumed to have the size of a P2PKH input with a maximal
+/// signature, so the estimate is an upper bound for transactions without JoinSplits.
+pub fn estimate_tx_size(
+    spends: usize,
+    outputs: usize,
+    transparent_inputs: usize,
+    transparent_outputs: usize,
+) -> usize {
+    let binding_sig = if spends > 0 || outputs > 0 {
+        BINDING_SIG_SIZE
+    } else {
+        0
+    };
+
+    V4_FIXED_SIZE
+        + compact_size_len(transparent_inputs)
+        + transparent_inputs * P2PKH_STANDARD_INPUT_SIZE
+        + compact_size_len(transparent_outputs)
+        + transparent_outputs * P2PKH_STANDARD_OUTPUT_SIZE
+        + compact_size_len(spends)
+        + spends * SPEND_DESCRIPTION_SIZE
+        + compact_size_len(outputs)
+        + outputs * OUTPUT_DESCRIPTION_SIZE
+        // nJoinSplit
+        + compact_size_len(0)
+        + binding_sig
+}
+
+/// Returns the number of logical actions, as defined in ZIP 317, for a transaction with
+/// the given components.
+///
+/// `transparent_input_size` and `transparent_output_size` are the total serialized
+/// sizes of the transparent inputs and outputs, excluding their count prefixes.
+pub fn logical_actions(
+    spends: usize,
+    outputs: usize,
+    joinsplits: usize,
+    transparent_input_size: usize,
+    transparent_output_size: usize,
+) -> usize {
+    let transparent = std::cmp::max(
+        ceil_div(transparent_input_size, P2PKH_STANDARD_INPUT_SIZE),
+        ceil_div(transparent_output_size, P2PKH_STANDARD_OUTPUT_SIZE),
+    );
+
+    transparent + std::cmp::max(spends, outputs) + 2 * joinsplits
+}
+
+impl Transaction {
+    /// Returns the number of logical actions in this transaction, as defined in ZIP 317.
+    pub fn logical_actions(&self) -> usize {
+        let mut buf = vec![];
+        for txin in &self.vin {
+            txin.write(&mut buf).unwrap();
+        }
+        let transparent_input_size = buf.len();
+
+        buf.clear();
+        for txout in &self.vout {
+            txout.write(&mut buf).unwrap();
+        }
+        let transparent_output_size = buf.len();
+
+        logical_actions(
+            self.shielded_spends.len(),
+            self.shielded_outputs.len(),
+            self.joinsplits.len(),
+            transparent_input_size,
+            transparent_output_size,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_tx_size, logical_actions, P2PKH_STANDARD_OUTPUT_SIZE};
+    use crate::transaction::{
+        components::{Amount, Script, TxOut},
+        TransactionData,
+    };
+
+    #[test]
+    fn transparent_outputs_size() {
+        let mut script = vec![0x76, 0xa9, 0x14];
+        script.extend_from_slice(&[0; 20]);
+        script.extend_from_slice(&[0x88, 0xac]);
+
+        let mut tx = TransactionData::new();
+        for _ in 0..3 {
+            tx.vout.push(TxOut {
+                value: Amount(1000),
+                script_pubkey: Script(script.clone()),
+            });
+        }
+        let tx = tx.freeze().unwrap();
+
+        let mut raw = vec![];
+        tx.write(&mut raw).unwrap();
+        assert_eq!(estimate_tx_size(0, 0, 0, 3), raw.len());
+        assert_eq!(tx.logical_actions(), 3);
+    }
+
+    #[test]
+    fn shielded_sizes() {
+        let empty = estimate_tx_size(0, 0, 0, 0);
+        assert_eq!(estimate_tx_size(1, 0, 0, 0), empty + 384 + 64);
+        assert_eq!(estimate_tx_size(0, 1, 0, 0), empty + 948 + 64);
+        assert_eq!(estimate_tx_size(0, 253, 0, 0), empty + 2 + 253 * 948 + 64);
+    }
+
+    #[test]
+    fn logical_action_counts() {
+        assert_eq!(logical_actions(0, 0, 0, 0, 0), 0);
+        assert_eq!(logical_actions(1, 2, 0, 0, 0), 2);
+        assert_eq!(logical_actions(0, 0, 1, 0, 0), 2);
+        assert_eq!(
+            logical_actions(0, 0, 0, 148, 2 * P2PKH_STANDARD_OUTPUT_SIZE + 1),
+            3
+        );
+        assert_eq!(logical_actions(2, 1, 0, 300, 34), 4);
+    }
+}
@@ -0,0 +1,431 @@
+//! Human-readable summaries of transactions.
+
+use std::fmt;
+
+use super::{
+    components::{Amount, Script, MAX_MONEY},
+    Transaction, TransactionData, TxId, OVERWINTER_TX_VERSION, OVERWINTER_VERSION_GROUP_ID,
+    SAPLING_TX_VERSION, SAPLING_VERSION_GROUP_ID,
+};
+#[cfg(feature = "zfuture")]
+use super::{ZFUTURE_TX_VERSION, ZFUTURE_VERSION_GROUP_ID};
+
+const OP_DUP: u8 = 0x76;
+const OP_HASH160: u8 = 0xa9;
+const OP_EQUAL: u8 = 0x87;
+const OP_EQUALVERIFY: u8 = 0x88;
+const OP_CHECKSIG: u8 = 0xac;
+const OP_RETURN: u8 = 0x6a;
+const OP_CHECKMULTISIG: u8 = 0xae;
+const OP_1: u8 = 0x51;
+const OP_16: u8 = 0x60;
+
+/// The transaction format, which determines the network upgrades under which a
+/// transaction can be mined.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TxFormat {
+    /// A pre-Overwinter (v1 or v2) transaction, valid before Overwinter activation.
+    Sprout(u32),
+    /// A v3 transaction, valid only while Overwinter is the current upgrade.
+    Overwinter,
+    /// A v4 transaction, valid from Sapling activation onwards.
+    Sapling,
+    /// A transaction in the experimental ZFUTURE format, which is not valid on any
+    /// public network.
+    #[cfg(feature = "zfuture")]
+    ZFuture,
+    /// An overwintered transaction with an unrecognised version or version group ID.
+    Unknown { version: u32, version_group_id: u32 },
+}
+
+impl fmt::Display for TxFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TxFormat::Sprout(version) => write!(f, "v{} (Sprout)", version),
+            TxFormat::Overwinter => write!(f, "v{} (Overwinter)", OVERWINTER_TX_VERSION),
+            TxFormat::Sapling => write!(f, "v{} (Sapling)", SAPLING_TX_VERSION),
+            #[cfg(feature = "zfuture")]
+            TxFormat::ZFuture => write!(f, "v{} (ZFUTURE)", ZFUTURE_TX_VERSION),
+            TxFormat::Unknown {
+                version,
+                version_group_id,
+            } => write!(
+                f,
+                "v{} (unknown version group ID {:#010x})",
+                version, version_group_id
+            ),
+        }
+    }
+}
+
+impl TxFormat {
+    /// Returns the format of the given transaction.
+    pub fn of(tx: &TransactionData) -> Self {
+        if !tx.overwintered {
+            return TxFormat::Sprout(tx.version);
+        }
+
+        #[cfg(feature = "zfuture")]
+        {
+            if tx.version_group_id == ZFUTURE_VERSION_GROUP_ID && tx.version == ZFUTURE_TX_VERSION {
+                return TxFormat::ZFuture;
+            }
+        }
+
+        if tx.version_group_id == OVERWINTER_VERSION_GROUP_ID && tx.version == OVERWINTER_TX_VERSION
+        {
+            TxFormat::Overwinter
+        } else if tx.version_group_id == SAPLING_VERSION_GROUP_ID
+            && tx.version == SAPLING_TX_VERSION
+        {
+            TxFormat::Sapling
+        } else {
+            TxFormat::Unknown {
+                version: tx.version,
+                version_group_id: tx.version_group_id,
+            }
+        }
+    }
+}
+
+/// The standard type of a transparent output script.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScriptType {
+    /// Pay to public key hash.
+    P2PKH,
+    /// Pay to script hash.
+    P2SH,
+    /// Pay to a compressed or uncompressed public key.
+    P2PK,
+    /// Bare multisig, requiring signatures from `required` of `keys` public keys.
+    Multisig { required: u8, keys: u8 },
+    /// A provably-unspendable `OP_RETURN` output.
+    NullData,
+    /// Any other script.
+    Nonstandard,
+}
+
+impl ScriptType {
+    /// Classifies the given `scriptPubKey`.
+    pub fn of(script: &Script) -> Self {
+        let s = &script.0;
+        if s.len() == 25
+            && s[0] == OP_DUP
+            && s[1] == OP_HASH160
+            && s[2] == 0x14
+            && s[23] == OP_EQUALVERIFY
+            && s[24] == OP_CHECKSIG
+        {
+            ScriptType::P2PKH
+        } else if s.len() == 23 && s[0] == OP_HASH160 && s[1] == 0x14 && s[22] == OP_EQUAL {
+            ScriptType::P2SH
+        } else if !s.is_empty() && s[0] == OP_RETURN {
+            ScriptType::NullData
+        } else if s.len() > 1
+            && pubkey_push_len(s) == Some(s.len() - 1)
+            && s[s.len() - 1] == OP_CHECKSIG
+        {
+            ScriptType::P2PK
+        } else if let Some((required, keys)) = multisig(s) {
+            ScriptType::Multisig { required, keys }
+        } else {
+            ScriptType::Nonstandard
+        }
+    }
+}
+
+/// Returns the length of the push of a 33- or 65-byte public key at the start of `s`.
+fn pubkey_push_len(s: &[u8]) -> Option<usize> {
+    match s.first() {
+        Some(&0x21) if s.len() >= 34 => Some(34),
+        Some(&0x41) if s.len() >= 66 => Some(66),
+        _ => None,
+    }
+}
+
+/// Parses `OP_m <pubkey>... OP_n OP_CHECKMULTISIG`, returning `(m, n)`.
+fn multisig(s: &[u8]) -> Option<(u8, u8)> {
+    let small_int = |op: u8| {
+        if op >= OP_1 && op <= OP_16 {
+            Some(op - OP_1 + 1)
+        } else {
+            None
+        }
+    };
+
+    if s.len() < 3 || s[s.len() - 1] != OP_CHECKMULTISIG {
+        return None;
+    }
+    let required = small_int(s[0])?;
+    let keys = small_int(s[s.len() - 2])?;
+
+    let mut pubkeys = &s[1..s.len() - 2];
+    let mut count = 0;
+    while !pubkeys.is_empty() {
+        pubkeys = &pubkeys[pubkey_push_len(pubkeys)?..];
+        count += 1;
+    }
+
+    if count == keys && required <= keys {
+        Some((required, keys))
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for ScriptType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ScriptType::P2PKH => "P2PKH",
+            ScriptType::P2SH => "P2SH",
+            ScriptType::P2PK => "P2PK",
+            ScriptType::Multisig { required, keys } => {
+                return write!(f, "{}-of-{} multisig", required, keys);
+            }
+            ScriptType::NullData => "OP_RETURN",
+            ScriptType::Nonstandard => "nonstandard",
+        })
+    }
+}
+
+/// Returns the sum of the given values, or `None` if it is greater than `MAX_MONEY`.
+fn sum_values<I: Iterator<Item = Amount>>(values: I) -> Option<Amount> {
+    let mut total = 0i64;
+    for value in values {
+        total = total
+            .checked_add(value.0)
+            .filter(|total| *total <= MAX_MONEY)?;
+    }
+    Some(Amount(total))
+}
+
+fn write_value(f: &mut fmt::Formatter, value: Option<Amount>) -> fmt::Result {
+    match value {
+        Some(value) => write!(f, "{}", value.0),
+        None => write!(f, "out of range"),
+    }
+}
+
+/// A summary of the structure and value flows of a [`Transaction`].
+///
+/// Shielded note contents (including memos) are encrypted, so they cannot be
+/// summarized without the corresponding viewing keys.
+#[derive(Debug)]
+pub struct TransactionSummary {
+    pub txid: TxId,
+    pub format: TxFormat,
+    pub lock_time: u32,
+    pub expiry_height: u32,
+    pub transparent_inputs: usize,
+    pub transparent_outputs: Vec<(Amount, ScriptType)>,
+    pub joinsplits: usize,
+    /// The total value moved from the transparent pool into the Sprout pool, or `None`
+    /// if it is greater than `MAX_MONEY`.
+    pub sprout_value_in: Option<Amount>,
+    /// The total value moved from the Sprout pool into the transparent pool, or `None`
+    /// if it is greater than `MAX_MONEY`.
+    pub sprout_value_out: Option<Amount>,
+    pub shielded_spends: usize,
+    pub shielded_outputs: usize,
+    /// The net value moved from the Sapling pool into the transparent pool.
+    pub value_balance: Amount,
+}
+
+impl TransactionSummary {
+    pub(crate) fn from_tx(tx: &Transaction) -> Self {
+        TransactionSummary {
+            txid: tx.txid(),
+            format: TxFormat::of(tx),
+            lock_time: tx.lock_time,
+            expiry_height: tx.expiry_height,
+            transparent_inputs: tx.vin.len(),
+            transparent_outputs: tx
+                .vout
+                .iter()
+                .map(|out| (out.value, ScriptType::of(&out.script_pubkey)))
+                .collect(),
+            joinsplits: tx.joinsplits.len(),
+            sprout_value_in: sum_values(tx.joinsplits.iter().map(|js| js.vpub_old)),
+            sprout_value_out: sum_values(tx.joinsplits.iter().map(|js| js.vpub_new)),
+            shielded_spends: tx.shielded_spends.len(),
+            shielded_outputs: tx.shielded_outputs.len(),
+            value_balance: tx.value_balance,
+        }
+    }
+
+    /// Returns the total value of the transparent outputs, or `None` if it is greater
+    /// than `MAX_MONEY`.
+    pub fn transparent_output_value(&self) -> Option<Amount> {
+        sum_values(self.transparent_outputs.iter().map(|(value, _)| *value))
+    }
+
+    /// Returns the fee paid by this transaction, given the total value of the
+    /// transparent outputs it spends (which is not recorded in the transaction).
+    ///
+    /// Returns `None` if any of the value flows of the transaction are out of range.
+    pub fn fee(&self, transparent_input_value: Amount) -> Option<Amount> {
+        transparent_input_value
+            .0
+            .checked_sub(self.transparent_output_value()?.0)?
+            .checked_add(self.sprout_value_out?.0)?
+            .checked_sub(self.sprout_value_in?.0)?
+            .checked_add(self.value_balance.0)
+            .map(Amount)
+    }
+}
+
+impl fmt::Display for TransactionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "txid: {}", self.txid)?;
+        writeln!(f, "format: {}", self.format)?;
+        writeln!(f, "lock time: {}", self.lock_time)?;
+        writeln!(f, "expiry height: {}", self.expiry_height)?;
+        writeln!(f, "transparent inputs: {}", self.transparent_inputs)?;
+        write!(
+            f,
+            "transparent outputs: {} (",
+            self.transparent_outputs.len()
+        )?;
+        write_value(f, self.transparent_output_value())?;
+        writeln!(f, " zatoshis)")?;
+        for (i, (value, script_type)) in self.transparent_outputs.iter().enumerate() {
+            writeln!(f, "  {}: {} zatoshis to {}", i, value.0, script_type)?;
+        }
+        write!(f, "joinsplits: {} (vpub_old ", self.joinsplits)?;
+        write_value(f, self.sprout_value_in)?;
+        write!(f, ", vpub_new ")?;
+        write_value(f, self.sprout_value_out)?;
+        writeln!(f, ")")?;
+        writeln!(f, "sapling spends: {}", self.shielded_spends)?;
+        writeln!(f, "sapling outputs: {}", self.shielded_outputs)?;
+        write!(f, "sapling value balance: {}", self.value_balance.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScriptType, TxFormat};
+    use crate::transaction::{
+        components::{Amount, Script, TxOut, MAX_MONEY},
+        TransactionData,
+    };
+
+    #[test]
+    fn script_types() {
+        let mut p2pkh = vec![0x76, 0xa9, 0x14];
+        p2pkh.extend_from_slice(&[0; 20]);
+        p2pkh.extend_from_slice(&[0x88, 0xac]);
+        assert_eq!(ScriptType::of(&Script(p2pkh)), ScriptType::P2PKH);
+
+        let mut p2sh = vec![0xa9, 0x14];
+        p2sh.extend_from_slice(&[0; 20]);
+        p2sh.push(0x87);
+        assert_eq!(ScriptType::of(&Script(p2sh)), ScriptType::P2SH);
+
+        assert_eq!(
+            ScriptType::of(&Script(vec![0x6a, 0x01, 0x00])),
+            ScriptType::NullData
+        );
+        assert_eq!(ScriptType::of(&Script(vec![])), ScriptType::Nonstandard);
+        assert_eq!(ScriptType::of(&Script(vec![0x51])), ScriptType::Nonstandard);
+    }
+
+    #[test]
+    fn pubkey_script_types() {
+        let pubkey = |len: u8| {
+            let mut push = vec![len];
+            push.extend_from_slice(&vec![2; len as usize]);
+            push
+        };
+
+        for len in &[33, 65] {
+            let mut p2pk = pubkey(*len);
+            p2pk.push(0xac);
+            assert_eq!(ScriptType::of(&Script(p2pk)), ScriptType::P2PK);
+        }
+        let mut p2pk = pubkey(32);
+        p2pk.push(0xac);
+        assert_eq!(ScriptType::of(&Script(p2pk)), ScriptType::Nonstandard);
+
+        // OP_2 <pubkey> <pubkey> <pubkey> OP_3 OP_CHECKMULTISIG
+        let mut multisig = vec![0x52];
+        multisig.extend(pubkey(33));
+        multisig.extend(pubkey(65));
+        multisig.extend(pubkey(33));
+        multisig.extend_from_slice(&[0x53, 0xae]);
+        assert_eq!(
+            ScriptType::of(&Script(multisig.clone())),
+            ScriptType::Multisig {
+                required: 2,
+                keys: 3
+            }
+        );
+        assert_eq!(
+            ScriptType::Multisig {
+                required: 2,
+                keys: 3
+            }
+            .to_string(),
+            "2-of-3 multisig"
+        );
+
+        // The number of keys must match, and at least as many as are required
+        let n = multisig.len() - 2;
+        multisig[n] = 0x52;
+        assert_eq!(
+            ScriptType::of(&Script(multisig.clone())),
+            ScriptType::Nonstandard
+        );
+        multisig[0] = 0x54;
+        multisig[n] = 0x53;
+        assert_eq!(ScriptType::of(&Script(multisig)), ScriptType::Nonstandard);
+    }
+
+    #[test]
+    fn describe_transparent_tx() {
+        let mut tx = TransactionData::new();
+        tx.vout.push(TxOut {
+            value: Amount(40000),
+            script_pubkey: Script(vec![0x6a]),
+        });
+        tx.vout.push(TxOut {
+            value: Amount(50000),
+            script_pubkey: Script(vec![0x51]),
+        });
+        tx.value_balance = Amount(-10000);
+        let tx = tx.freeze().unwrap();
+
+        let summary = tx.describe();
+        assert_eq!(summary.txid, tx.txid());
+        assert_eq!(summary.format, TxFormat::Sapling);
+        assert_eq!(summary.transparent_inputs, 0);
+        assert_eq!(
+            summary.transparent_outputs,
+            vec![
+                (Amount(40000), ScriptType::NullData),
+                (Amount(50000), ScriptType::Nonstandard),
+            ]
+        );
+        assert_eq!(summary.transparent_output_value(), Some(Amount(90000)));
+        assert_eq!(summary.fee(Amount(110000)), Some(Amount(10000)));
+    }
+
+    #[test]
+    fn out_of_range_values() {
+        let mut tx = TransactionData::new();
+        for _ in 0..2 {
+            tx.vout.push(TxOut {
+                value: Amount(MAX_MONEY),
+                script_pubkey: Script(vec![0x51]),
+            });
+        }
+        let tx = tx.freeze().unwrap();
+
+        let summary = tx.describe();
+        assert_eq!(summary.transparent_output_value(), None);
+        assert_eq!(summary.fee(Amount(0)), None);
+        assert!(summary
+            .to_string()
+            .contains("transparent outputs: 2 (out of range zatoshis)"));
+    }
+}
@@ -8,6 +8,7 @@ use sapling_crypto::{
 
 use super::{
     components::{Amount, Script},
+    joinsplits_hash, outputs_hash, prevout_hash, sequence_hash,
     sighash::signature_hash,
     Transaction, TransactionData,
 };
@@ -5407,3 +5408,131 @@ fn zip_0243() {
         );
     }
 }
+
+#[test]
+fn sighash_component_hashes_of_empty_tx() {
+    // BLAKE2b-256 of the empty string under each personalization
+    let tx = TransactionData::new();
+
+    assert_eq!(
+        prevout_hash(&tx).as_ref(),
+        &[
+            0xd5, 0x3a, 0x63, 0x3b, 0xbe, 0xcf, 0x82, 0xfe, 0x9e, 0x94, 0x84, 0xd8, 0xa0, 0xe7,
+            0x27, 0xc7, 0x3b, 0xb9, 0xe6, 0x8c, 0x96, 0xe7, 0x2d, 0xec, 0x30, 0x14, 0x4f, 0x6a,
+            0x84, 0xaf, 0xa1, 0x36,
+        ][..]
+    );
+    assert_eq!(
+        sequence_hash(&tx).as_ref(),
+        &[
+            0xa5, 0xf2, 0x5f, 0x01, 0x95, 0x93, 0x61, 0xee, 0x6e, 0xb5, 0x6a, 0x74, 0x01, 0x21,
+            0x0e, 0xe2, 0x68, 0x22, 0x6f, 0x6c, 0xe7, 0x64, 0xa4, 0xf1, 0x0b, 0x7f, 0x29, 0xe5,
+            0x4d, 0xb3, 0x72, 0x72,
+        ][..]
+    );
+    assert_eq!(
+        outputs_hash(&tx).as_ref(),
+        &[
+            0x86, 0x9e, 0xda, 0x84, 0xee, 0xcf, 0x72, 0x57, 0xf9, 0x97, 0x9a, 0x48, 0x48, 0xbb,
+            0xf5, 0x2f, 0x49, 0x69, 0xa5, 0x73, 0x65, 0x94, 0xab, 0x7b, 0xa4, 0x14, 0x52, 0xe7,
+            0xbb, 0x90, 0x68, 0x24,
+        ][..]
+    );
+
+    // Without a joinSplitPubKey, there is nothing to hash
+    assert_eq!(
+        joinsplits_hash(&tx).as_ref(),
+        &[
+            0xaf, 0xc8, 0x91, 0x26, 0x47, 0x9d, 0xda, 0x44, 0x16, 0x7e, 0xcf, 0xbc, 0x40, 0xe0,
+            0x16, 0xb7, 0xdc, 0x09, 0x2e, 0x5c, 0x85, 0x68, 0x56, 0xea, 0x40, 0x6b, 0xfa, 0x9b,
+            0xa8, 0x26, 0x10, 0x28,
+        ][..]
+    );
+}
+
+#[cfg(feature = "zfuture")]
+#[test]
+fn zfuture_tx_read_write() {
+    use super::{
+        components::TxOut,
+        tze::{Precondition, TzeOut},
+        ZFUTURE_TX_VERSION, ZFUTURE_VERSION_GROUP_ID,
+    };
+
+    let mut tx = TransactionData::new();
+    tx.version = ZFUTURE_TX_VERSION;
+    tx.version_group_id = ZFUTURE_VERSION_GROUP_ID;
+    tx.vout.push(TxOut {
+        value: Amount(1000),
+        script_pubkey: Script(vec![0x51]),
+    });
+    tx.tze_outputs.push(TzeOut {
+        value: Amount(2000),
+        precondition: Precondition {
+            extension_id: 0,
+            mode: 1,
+            payload: vec![7; 32],
+        },
+    });
+    tx.expiry_height = 10;
+    let tx = tx.freeze().unwrap();
+
+    let mut encoded = vec![];
+    tx.write(&mut encoded).unwrap();
+    let decoded = Transaction::read(&encoded[..]).unwrap();
+    assert_eq!(decoded.txid(), tx.txid());
+    assert!(decoded.tze_inputs.is_empty());
+    assert_eq!(decoded.tze_outputs.len(), 1);
+    assert_eq!(decoded.tze_outputs[0].value, Amount(2000));
+    assert_eq!(
+        decoded.tze_outputs[0].precondition,
+        tx.tze_outputs[0].precondition
+    );
+    assert_eq!(decoded.expiry_height, 10);
+
+    // TZE fields can't be serialized in other transaction formats
+    let mut tx = TransactionData::new();
+    tx.tze_outputs.push(TzeOut {
+        value: Amount(2000),
+        precondition: Precondition {
+            extension_id: 0,
+            mode: 1,
+            payload: vec![],
+        },
+    });
+    assert!(tx.freeze().is_err());
+}
+
+#[cfg(feature = "zfuture")]
+#[test]
+fn zfuture_signature_hash() {
+    use super::{
+        summary::TxFormat,
+        tze::{Precondition, TzeOut},
+        SIGHASH_ALL, ZFUTURE_TX_VERSION, ZFUTURE_VERSION_GROUP_ID,
+    };
+
+    let zfuture_tx = |payload: Vec<u8>| {
+        let mut tx = TransactionData::new();
+        tx.version = ZFUTURE_TX_VERSION;
+        tx.version_group_id = ZFUTURE_VERSION_GROUP_ID;
+        tx.tze_outputs.push(TzeOut {
+            value: Amount(2000),
+            precondition: Precondition {
+                extension_id: 0,
+                mode: 1,
+                payload,
+            },
+        });
+        tx.freeze().unwrap()
+    };
+
+    let tx = zfuture_tx(vec![7; 32]);
+    assert_eq!(tx.describe().format, TxFormat::ZFuture);
+
+    // The signature hash commits to the TZE outputs
+    assert_ne!(
+        signature_hash(&tx, 0, SIGHASH_ALL, None),
+        signature_hash(&zfuture_tx(vec![8; 32]), 0, SIGHASH_ALL, None)
+    );
+}
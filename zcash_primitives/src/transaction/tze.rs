@@ -0,0 +1,132 @@
+//! Placeholder structures for Transparent Zcash Extensions (ZIP 222).
+//!
+//! These only support parsing and serializing the TZE fields of the experimental
+//! `ZFUTURE` transaction format. No extension semantics are implemented.
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+use serialize::{CompactSize, Vector};
+
+use super::components::{Amount, OutPoint};
+
+fn read_u32_compact<R: Read>(reader: R) -> io::Result<u32> {
+    let n = CompactSize::read(reader)?;
+    if n > u32::max_value() as usize {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "CompactSize value out of range for a TZE identifier",
+        ))
+    } else {
+        Ok(n as u32)
+    }
+}
+
+/// Reads the `(extension_id, mode, payload)` encoding shared by preconditions and
+/// witnesses.
+fn read_parts<R: Read>(mut reader: R) -> io::Result<(u32, u32, Vec<u8>)> {
+    let extension_id = read_u32_compact(&mut reader)?;
+    let mode = read_u32_compact(&mut reader)?;
+    let payload = Vector::read(&mut reader, |r| r.read_u8())?;
+    Ok((extension_id, mode, payload))
+}
+
+fn write_parts<W: Write>(
+    mut writer: W,
+    extension_id: u32,
+    mode: u32,
+    payload: &[u8],
+) -> io::Result<()> {
+    CompactSize::write(&mut writer, extension_id as usize)?;
+    CompactSize::write(&mut writer, mode as usize)?;
+    Vector::write(&mut writer, payload, |w, e| w.write_u8(*e))
+}
+
+/// The condition that must be satisfied in order to spend a TZE output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Precondition {
+    pub extension_id: u32,
+    pub mode: u32,
+    pub payload: Vec<u8>,
+}
+
+impl Precondition {
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        let (extension_id, mode, payload) = read_parts(reader)?;
+        Ok(Precondition {
+            extension_id,
+            mode,
+            payload,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_parts(writer, self.extension_id, self.mode, &self.payload)
+    }
+}
+
+/// The data used to satisfy the [`Precondition`] of a TZE output being spent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Witness {
+    pub extension_id: u32,
+    pub mode: u32,
+    pub payload: Vec<u8>,
+}
+
+impl Witness {
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        let (extension_id, mode, payload) = read_parts(reader)?;
+        Ok(Witness {
+            extension_id,
+            mode,
+            payload,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_parts(writer, self.extension_id, self.mode, &self.payload)
+    }
+}
+
+#[derive(Debug)]
+pub struct TzeIn {
+    pub prevout: OutPoint,
+    pub witness: Witness,
+}
+
+impl TzeIn {
+    pub fn read<R: Read>(mut reader: &mut R) -> io::Result<Self> {
+        let prevout = OutPoint::read(&mut reader)?;
+        let witness = Witness::read(&mut reader)?;
+
+        Ok(TzeIn { prevout, witness })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.prevout.write(&mut writer)?;
+        self.witness.write(&mut writer)
+    }
+}
+
+#[derive(Debug)]
+pub struct TzeOut {
+    pub value: Amount,
+    pub precondition: Precondition,
+}
+
+impl TzeOut {
+    pub fn read<R: Read>(mut reader: &mut R) -> io::Result<Self> {
+        let value = Amount::read_i64(&mut reader, false)?;
+        let precondition = Precondition::read(&mut reader)?;
+
+        Ok(TzeOut {
+            value,
+            precondition,
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_i64::<LittleEndian>(self.value.0)?;
+        self.precondition.write(&mut writer)
+    }
+}
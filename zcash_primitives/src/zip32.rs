@@ -15,8 +15,10 @@ use crate::{
     JUBJUB,
 };
 
-pub const ZIP32_SAPLING_MASTER_PERSONALIZATION: &'static [u8; 16] = b"ZcashIP32Sapling";
-pub const ZIP32_SAPLING_FVFP_PERSONALIZATION: &'static [u8; 16] = b"ZcashSaplingFVFP";
+pub use crate::constants::{
+    ZIP32_SAPLING_FVFP_PERSONALIZATION, ZIP32_SAPLING_INT_PERSONALIZATION,
+    ZIP32_SAPLING_MASTER_PERSONALIZATION,
+};
 
 // Common helper functions
 
@@ -26,6 +28,34 @@ fn derive_child_ovk(parent: &OutgoingViewingKey, i_l: &[u8]) -> OutgoingViewingK
     OutgoingViewingKey(ovk)
 }
 
+/// Returns `(I_nsk, dk_internal, ovk_internal)` for the internal key corresponding to the
+/// given full viewing key and diversifier key.
+fn derive_internal_parts(
+    fvk: &FullViewingKey<Bls12>,
+    dk: &DiversifierKey,
+) -> (Fs, DiversifierKey, OutgoingViewingKey) {
+    let i = Blake2bParams::new()
+        .hash_length(32)
+        .personal(ZIP32_SAPLING_INT_PERSONALIZATION)
+        .to_state()
+        .update(&fvk.to_bytes())
+        .update(&dk.0)
+        .finalize();
+
+    let i_nsk = Fs::to_uniform(prf_expand(i.as_bytes(), &[0x17]).as_bytes());
+    let r = prf_expand(i.as_bytes(), &[0x18]);
+    let mut dk_internal = [0u8; 32];
+    let mut ovk_internal = [0u8; 32];
+    dk_internal.copy_from_slice(&r.as_bytes()[..32]);
+    ovk_internal.copy_from_slice(&r.as_bytes()[32..]);
+
+    (
+        i_nsk,
+        DiversifierKey(dk_internal),
+        OutgoingViewingKey(ovk_internal),
+    )
+}
+
 // ZIP 32 structures
 
 /// A Sapling full viewing key fingerprint
@@ -156,6 +186,19 @@ impl DiversifierKey {
             }
         }
     }
+
+    /// Returns the diversifier index to which this key maps the given diversifier.
+    ///
+    /// This is the inverse of [`DiversifierKey::diversifier`] for valid diversifiers.
+    pub fn diversifier_index(&self, d: &Diversifier) -> DiversifierIndex {
+        let ff = FF1::<Aes256>::new(&self.0, 2).unwrap();
+        let dec = ff
+            .decrypt(&[], &BinaryNumeralString::from_bytes_le(&d.0[..]))
+            .unwrap();
+        let mut j = DiversifierIndex::new();
+        j.0.copy_from_slice(&dec.to_bytes_le());
+        j
+    }
 }
 
 /// A Sapling extended spending key
@@ -332,6 +375,33 @@ impl ExtendedSpendingKey {
     pub fn default_address(&self) -> Result<(DiversifierIndex, PaymentAddress<Bls12>), ()> {
         ExtendedFullViewingKey::from(self).default_address()
     }
+
+    /// Derives the internal (change) key corresponding to this external key, as
+    /// specified in ZIP 32.
+    ///
+    /// The internal key has the same spend authorizing key, but its own nullifier
+    /// deriving key, outgoing viewing key and diversifier key, so notes sent to it are
+    /// not detectable with the external key's incoming viewing key.
+    pub fn derive_internal(&self) -> Self {
+        let fvk = FullViewingKey::from_expanded_spending_key(&self.expsk, &JUBJUB);
+        let (i_nsk, dk, ovk) = derive_internal_parts(&fvk, &self.dk);
+
+        let mut nsk = i_nsk;
+        nsk.add_assign(&self.expsk.nsk);
+
+        ExtendedSpendingKey {
+            depth: self.depth,
+            parent_fvk_tag: self.parent_fvk_tag,
+            child_index: self.child_index,
+            chain_code: self.chain_code,
+            expsk: ExpandedSpendingKey {
+                ask: self.expsk.ask,
+                nsk,
+                ovk,
+            },
+            dk,
+        }
+    }
 }
 
 impl<'a> From<&'a ExtendedSpendingKey> for ExtendedFullViewingKey {
@@ -439,6 +509,48 @@ impl ExtendedFullViewingKey {
     pub fn default_address(&self) -> Result<(DiversifierIndex, PaymentAddress<Bls12>), ()> {
         self.address(DiversifierIndex::new())
     }
+
+    /// Derives the internal (change) key corresponding to this external key, as
+    /// specified in ZIP 32.
+    pub fn derive_internal(&self) -> Self {
+        let (i_nsk, dk, ovk) = derive_internal_parts(&self.fvk, &self.dk);
+
+        let nk = JUBJUB
+            .generator(FixedGenerators::ProofGenerationKey)
+            .mul(i_nsk, &JUBJUB)
+            .add(&self.fvk.vk.nk, &JUBJUB);
+
+        ExtendedFullViewingKey {
+            depth: self.depth,
+            parent_fvk_tag: self.parent_fvk_tag,
+            child_index: self.child_index,
+            chain_code: self.chain_code,
+            fvk: FullViewingKey {
+                vk: ViewingKey {
+                    ak: self.fvk.vk.ak.clone(),
+                    nk,
+                },
+                ovk,
+            },
+            dk,
+        }
+    }
+
+    /// Returns the ZIP 32 fingerprint of this key's full viewing key.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        FVKFingerprint::from(&self.fvk).0
+    }
+
+    /// Returns the diversifier index of the given address, or `None` if the address was
+    /// not derived from this key.
+    pub fn address_index(&self, addr: &PaymentAddress<Bls12>) -> Option<DiversifierIndex> {
+        match self.fvk.vk.into_payment_address(addr.diversifier, &JUBJUB) {
+            Some(ref derived) if derived == addr => {
+                Some(self.dk.diversifier_index(&addr.diversifier))
+            }
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -550,6 +662,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn address_index() {
+        let xfvk = ExtendedFullViewingKey::from(&ExtendedSpendingKey::master(&[0; 32]));
+        let other = ExtendedFullViewingKey::from(&ExtendedSpendingKey::master(&[1; 32]));
+
+        let (j_5, addr_5) = xfvk
+            .address(DiversifierIndex([5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]))
+            .unwrap();
+        assert_eq!(xfvk.address_index(&addr_5), Some(j_5));
+        assert_eq!(other.address_index(&addr_5), None);
+
+        let (j_0, addr_0) = xfvk.default_address().unwrap();
+        assert_eq!(xfvk.address_index(&addr_0), Some(j_0));
+    }
+
+    #[test]
+    fn derive_internal() {
+        let xsk = ExtendedSpendingKey::master(&[0; 32]);
+        let xfvk = ExtendedFullViewingKey::from(&xsk);
+
+        let xsk_int = xsk.derive_internal();
+        let xfvk_int = xfvk.derive_internal();
+        assert_eq!(ExtendedFullViewingKey::from(&xsk_int), xfvk_int);
+
+        // The internal key shares ak, but nothing else that determines addresses
+        assert_eq!(xfvk_int.fvk.vk.ak, xfvk.fvk.vk.ak);
+        assert!(xfvk_int.fvk.vk.nk != xfvk.fvk.vk.nk);
+        assert!(xfvk_int.fvk.ovk != xfvk.fvk.ovk);
+        assert!(xfvk_int.dk != xfvk.dk);
+
+        let (_, addr_int) = xfvk_int.default_address().unwrap();
+        assert!(xfvk_int.address_index(&addr_int).is_some());
+        assert_eq!(xfvk.address_index(&addr_int), None);
+    }
+
     #[test]
     fn read_write() {
         let seed = [0; 32];
@@ -1081,4 +1228,221 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn internal_test_vectors() {
+        struct InternalTestVector {
+            nsk: [u8; 32],
+            ovk: [u8; 32],
+            dk: [u8; 32],
+            nk: [u8; 32],
+            xsk: [u8; 169],
+            xfvk: [u8; 169],
+        }
+
+        // Internal keys for the first three keys in test_vectors. These are regression
+        // values computed with an independent implementation of ZIP 32 internal key
+        // derivation, not the published zcash-test-vectors internal vectors, which
+        // should replace them.
+        let test_vectors = vec![
+            InternalTestVector {
+                nsk: [
+                    0x51, 0x12, 0x33, 0x63, 0x6b, 0x95, 0xfd, 0x0a, 0xfb, 0x6b, 0xf8, 0x19, 0x3a,
+                    0x7d, 0x8f, 0x49, 0xef, 0xd7, 0x36, 0xa9, 0x88, 0x77, 0x5c, 0x54, 0xf9, 0x56,
+                    0x68, 0x76, 0x46, 0xea, 0xab, 0x07,
+                ],
+                ovk: [
+                    0x9d, 0xc4, 0x77, 0xfe, 0x1e, 0x7d, 0x28, 0x29, 0x13, 0xf6, 0x51, 0x65, 0x4d,
+                    0x39, 0x85, 0xf0, 0x9d, 0x53, 0xc2, 0xd3, 0xb5, 0x76, 0x3d, 0x7a, 0x72, 0x3b,
+                    0xcb, 0xd6, 0xee, 0x05, 0x3d, 0x5a,
+                ],
+                dk: [
+                    0x40, 0xdd, 0xc5, 0x6e, 0x69, 0x75, 0x13, 0x8c, 0x08, 0x39, 0xe5, 0x80, 0xb5,
+                    0x4d, 0x6d, 0x99, 0x9d, 0xc6, 0x16, 0x84, 0x3c, 0xfe, 0x04, 0x1e, 0x8f, 0x38,
+                    0x8b, 0x12, 0x4e, 0xf7, 0xb5, 0xed,
+                ],
+                nk: [
+                    0xa3, 0x83, 0x1a, 0x5c, 0x69, 0x33, 0xf8, 0xec, 0x6a, 0xa5, 0xce, 0x31, 0x6c,
+                    0x50, 0x8b, 0x79, 0x91, 0xcd, 0x94, 0xd3, 0xbd, 0xb7, 0x00, 0xa1, 0xc4, 0x27,
+                    0xa6, 0xae, 0x15, 0xe7, 0x2f, 0xb5,
+                ],
+                xsk: [
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xd0, 0x94, 0x7c, 0x4b,
+                    0x03, 0xbf, 0x72, 0xa3, 0x7a, 0xb4, 0x4f, 0x72, 0x27, 0x6d, 0x1c, 0xf3, 0xfd,
+                    0xcd, 0x7e, 0xbf, 0x3e, 0x73, 0x34, 0x8b, 0x7e, 0x55, 0x0d, 0x75, 0x20, 0x18,
+                    0x66, 0x8e, 0xb6, 0xc0, 0x0c, 0x93, 0xd3, 0x60, 0x32, 0xb9, 0xa2, 0x68, 0xe9,
+                    0x9e, 0x86, 0xa8, 0x60, 0x77, 0x65, 0x60, 0xbf, 0x0e, 0x83, 0xc1, 0xa1, 0x0b,
+                    0x51, 0xf6, 0x07, 0xc9, 0x54, 0x74, 0x25, 0x06, 0x51, 0x12, 0x33, 0x63, 0x6b,
+                    0x95, 0xfd, 0x0a, 0xfb, 0x6b, 0xf8, 0x19, 0x3a, 0x7d, 0x8f, 0x49, 0xef, 0xd7,
+                    0x36, 0xa9, 0x88, 0x77, 0x5c, 0x54, 0xf9, 0x56, 0x68, 0x76, 0x46, 0xea, 0xab,
+                    0x07, 0x9d, 0xc4, 0x77, 0xfe, 0x1e, 0x7d, 0x28, 0x29, 0x13, 0xf6, 0x51, 0x65,
+                    0x4d, 0x39, 0x85, 0xf0, 0x9d, 0x53, 0xc2, 0xd3, 0xb5, 0x76, 0x3d, 0x7a, 0x72,
+                    0x3b, 0xcb, 0xd6, 0xee, 0x05, 0x3d, 0x5a, 0x40, 0xdd, 0xc5, 0x6e, 0x69, 0x75,
+                    0x13, 0x8c, 0x08, 0x39, 0xe5, 0x80, 0xb5, 0x4d, 0x6d, 0x99, 0x9d, 0xc6, 0x16,
+                    0x84, 0x3c, 0xfe, 0x04, 0x1e, 0x8f, 0x38, 0x8b, 0x12, 0x4e, 0xf7, 0xb5, 0xed,
+                ],
+                xfvk: [
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xd0, 0x94, 0x7c, 0x4b,
+                    0x03, 0xbf, 0x72, 0xa3, 0x7a, 0xb4, 0x4f, 0x72, 0x27, 0x6d, 0x1c, 0xf3, 0xfd,
+                    0xcd, 0x7e, 0xbf, 0x3e, 0x73, 0x34, 0x8b, 0x7e, 0x55, 0x0d, 0x75, 0x20, 0x18,
+                    0x66, 0x8e, 0x93, 0x44, 0x2e, 0x5f, 0xef, 0xfb, 0xff, 0x16, 0xe7, 0x21, 0x72,
+                    0x02, 0xdc, 0x73, 0x06, 0x72, 0x9f, 0xff, 0xfe, 0x85, 0xaf, 0x56, 0x83, 0xbc,
+                    0xe2, 0x64, 0x2e, 0x3e, 0xeb, 0x5d, 0x38, 0x71, 0xa3, 0x83, 0x1a, 0x5c, 0x69,
+                    0x33, 0xf8, 0xec, 0x6a, 0xa5, 0xce, 0x31, 0x6c, 0x50, 0x8b, 0x79, 0x91, 0xcd,
+                    0x94, 0xd3, 0xbd, 0xb7, 0x00, 0xa1, 0xc4, 0x27, 0xa6, 0xae, 0x15, 0xe7, 0x2f,
+                    0xb5, 0x9d, 0xc4, 0x77, 0xfe, 0x1e, 0x7d, 0x28, 0x29, 0x13, 0xf6, 0x51, 0x65,
+                    0x4d, 0x39, 0x85, 0xf0, 0x9d, 0x53, 0xc2, 0xd3, 0xb5, 0x76, 0x3d, 0x7a, 0x72,
+                    0x3b, 0xcb, 0xd6, 0xee, 0x05, 0x3d, 0x5a, 0x40, 0xdd, 0xc5, 0x6e, 0x69, 0x75,
+                    0x13, 0x8c, 0x08, 0x39, 0xe5, 0x80, 0xb5, 0x4d, 0x6d, 0x99, 0x9d, 0xc6, 0x16,
+                    0x84, 0x3c, 0xfe, 0x04, 0x1e, 0x8f, 0x38, 0x8b, 0x12, 0x4e, 0xf7, 0xb5, 0xed,
+                ],
+            },
+            InternalTestVector {
+                nsk: [
+                    0x74, 0x92, 0x9f, 0x79, 0x0c, 0x11, 0xdc, 0xab, 0x3a, 0x2f, 0x93, 0x12, 0x35,
+                    0xcd, 0xb2, 0x67, 0xf5, 0xa3, 0x1b, 0x9f, 0x13, 0x9f, 0x2c, 0x9f, 0xd8, 0x16,
+                    0xb0, 0x44, 0x4f, 0xb8, 0x05, 0x05,
+                ],
+                ovk: [
+                    0x0c, 0xd4, 0xd7, 0xc5, 0xcc, 0x7f, 0x53, 0x4b, 0x96, 0xd2, 0x41, 0x82, 0xa3,
+                    0x14, 0x65, 0xb4, 0x78, 0x11, 0x05, 0x48, 0x9c, 0xd1, 0x0d, 0x50, 0x0c, 0xf5,
+                    0x29, 0x5a, 0x6f, 0xd8, 0x18, 0xcc,
+                ],
+                dk: [
+                    0xd2, 0x78, 0xb7, 0x2c, 0x62, 0x1d, 0x19, 0xcb, 0x00, 0xf9, 0x70, 0x07, 0x9c,
+                    0x89, 0x22, 0x76, 0x1c, 0xdd, 0x3a, 0xe7, 0xf2, 0x7b, 0x18, 0x47, 0xc5, 0x53,
+                    0x60, 0xdb, 0xeb, 0xf6, 0x54, 0x92,
+                ],
+                nk: [
+                    0x2b, 0x5c, 0x78, 0xa2, 0xfb, 0xa5, 0x01, 0x9c, 0x15, 0xa7, 0x51, 0x50, 0x2b,
+                    0xa9, 0x91, 0x6f, 0xae, 0xda, 0xe1, 0xfc, 0x14, 0xdc, 0x81, 0xb0, 0xb8, 0x35,
+                    0xf2, 0xbf, 0x95, 0xc0, 0x68, 0xe8,
+                ],
+                xsk: [
+                    0x01, 0x14, 0xc2, 0x71, 0x3a, 0x01, 0x00, 0x00, 0x00, 0x01, 0x47, 0x11, 0x0c,
+                    0x69, 0x1a, 0x03, 0xb9, 0xd9, 0xf0, 0xba, 0x90, 0x05, 0xc5, 0xe7, 0x90, 0xa5,
+                    0x95, 0xb7, 0xf0, 0x4e, 0x33, 0x29, 0xd2, 0xfa, 0x43, 0x8a, 0x67, 0x05, 0xda,
+                    0xbc, 0xe6, 0x28, 0x2b, 0xc1, 0x97, 0xa5, 0x16, 0x28, 0x7c, 0x8e, 0xa8, 0xf6,
+                    0x8c, 0x42, 0x4a, 0xba, 0xd3, 0x02, 0xb4, 0x5c, 0xdf, 0x95, 0x40, 0x79, 0x61,
+                    0xd7, 0xb8, 0xb4, 0x55, 0x26, 0x7a, 0x35, 0x0c, 0x74, 0x92, 0x9f, 0x79, 0x0c,
+                    0x11, 0xdc, 0xab, 0x3a, 0x2f, 0x93, 0x12, 0x35, 0xcd, 0xb2, 0x67, 0xf5, 0xa3,
+                    0x1b, 0x9f, 0x13, 0x9f, 0x2c, 0x9f, 0xd8, 0x16, 0xb0, 0x44, 0x4f, 0xb8, 0x05,
+                    0x05, 0x0c, 0xd4, 0xd7, 0xc5, 0xcc, 0x7f, 0x53, 0x4b, 0x96, 0xd2, 0x41, 0x82,
+                    0xa3, 0x14, 0x65, 0xb4, 0x78, 0x11, 0x05, 0x48, 0x9c, 0xd1, 0x0d, 0x50, 0x0c,
+                    0xf5, 0x29, 0x5a, 0x6f, 0xd8, 0x18, 0xcc, 0xd2, 0x78, 0xb7, 0x2c, 0x62, 0x1d,
+                    0x19, 0xcb, 0x00, 0xf9, 0x70, 0x07, 0x9c, 0x89, 0x22, 0x76, 0x1c, 0xdd, 0x3a,
+                    0xe7, 0xf2, 0x7b, 0x18, 0x47, 0xc5, 0x53, 0x60, 0xdb, 0xeb, 0xf6, 0x54, 0x92,
+                ],
+                xfvk: [
+                    0x01, 0x14, 0xc2, 0x71, 0x3a, 0x01, 0x00, 0x00, 0x00, 0x01, 0x47, 0x11, 0x0c,
+                    0x69, 0x1a, 0x03, 0xb9, 0xd9, 0xf0, 0xba, 0x90, 0x05, 0xc5, 0xe7, 0x90, 0xa5,
+                    0x95, 0xb7, 0xf0, 0x4e, 0x33, 0x29, 0xd2, 0xfa, 0x43, 0x8a, 0x67, 0x05, 0xda,
+                    0xbc, 0xe6, 0xdc, 0x14, 0xb5, 0x14, 0xd3, 0xa9, 0x25, 0x94, 0xc2, 0x19, 0x25,
+                    0xaf, 0x2f, 0x77, 0x65, 0xa5, 0x47, 0xb3, 0x0e, 0x73, 0xfa, 0x7b, 0x70, 0x0e,
+                    0xa1, 0xbf, 0xf2, 0xe5, 0xef, 0xaa, 0xa8, 0x8b, 0x2b, 0x5c, 0x78, 0xa2, 0xfb,
+                    0xa5, 0x01, 0x9c, 0x15, 0xa7, 0x51, 0x50, 0x2b, 0xa9, 0x91, 0x6f, 0xae, 0xda,
+                    0xe1, 0xfc, 0x14, 0xdc, 0x81, 0xb0, 0xb8, 0x35, 0xf2, 0xbf, 0x95, 0xc0, 0x68,
+                    0xe8, 0x0c, 0xd4, 0xd7, 0xc5, 0xcc, 0x7f, 0x53, 0x4b, 0x96, 0xd2, 0x41, 0x82,
+                    0xa3, 0x14, 0x65, 0xb4, 0x78, 0x11, 0x05, 0x48, 0x9c, 0xd1, 0x0d, 0x50, 0x0c,
+                    0xf5, 0x29, 0x5a, 0x6f, 0xd8, 0x18, 0xcc, 0xd2, 0x78, 0xb7, 0x2c, 0x62, 0x1d,
+                    0x19, 0xcb, 0x00, 0xf9, 0x70, 0x07, 0x9c, 0x89, 0x22, 0x76, 0x1c, 0xdd, 0x3a,
+                    0xe7, 0xf2, 0x7b, 0x18, 0x47, 0xc5, 0x53, 0x60, 0xdb, 0xeb, 0xf6, 0x54, 0x92,
+                ],
+            },
+            InternalTestVector {
+                nsk: [
+                    0x34, 0x78, 0x65, 0xac, 0xf4, 0x7e, 0x50, 0x45, 0x38, 0xf5, 0xef, 0x8b, 0x04,
+                    0x70, 0x20, 0x80, 0xe6, 0x09, 0x1c, 0xda, 0x57, 0x97, 0xcd, 0x7d, 0x23, 0x5a,
+                    0x54, 0x6e, 0xb1, 0x0f, 0x55, 0x08,
+                ],
+                ovk: [
+                    0xdd, 0xba, 0xc2, 0xa4, 0x93, 0xf5, 0x3c, 0x3b, 0x09, 0x33, 0xd9, 0x13, 0xde,
+                    0xf8, 0x88, 0x48, 0x65, 0x4c, 0x08, 0x7c, 0x12, 0x60, 0x9d, 0xf0, 0x1b, 0xaf,
+                    0x94, 0x05, 0xce, 0x78, 0x04, 0xfd,
+                ],
+                dk: [
+                    0x60, 0x2c, 0xd3, 0x17, 0xb7, 0xce, 0xa1, 0x1e, 0x8c, 0xc7, 0xae, 0x2e, 0xa4,
+                    0x05, 0xb4, 0x0d, 0x46, 0xb1, 0x59, 0x2a, 0x30, 0xf0, 0xcb, 0x6e, 0x8c, 0x4f,
+                    0x17, 0xd7, 0xf7, 0xc4, 0x7f, 0xeb,
+                ],
+                nk: [
+                    0xf9, 0x12, 0x87, 0xc0, 0x6e, 0x30, 0xb6, 0x5e, 0xa1, 0xbd, 0xb7, 0x16, 0xb2,
+                    0x31, 0xde, 0x67, 0x78, 0xa5, 0xd8, 0x0e, 0xe5, 0xcd, 0x9c, 0x06, 0x0d, 0x1a,
+                    0xba, 0xca, 0xe0, 0xaa, 0xe2, 0x3b,
+                ],
+                xsk: [
+                    0x02, 0xdb, 0x99, 0x9e, 0x07, 0x02, 0x00, 0x00, 0x80, 0x97, 0xce, 0x15, 0xf4,
+                    0xed, 0x1b, 0x97, 0x39, 0xb0, 0x26, 0x2a, 0x46, 0x3b, 0xcb, 0x3d, 0xc9, 0xb3,
+                    0xbd, 0x23, 0x23, 0xa9, 0xba, 0xa4, 0x41, 0xca, 0x42, 0x77, 0x73, 0x83, 0xa8,
+                    0xd4, 0x35, 0x8b, 0xe8, 0x11, 0x3c, 0xee, 0x34, 0x13, 0xa7, 0x1f, 0x82, 0xc4,
+                    0x1f, 0xc8, 0xda, 0x51, 0x7b, 0xe1, 0x34, 0x04, 0x98, 0x32, 0xe6, 0x82, 0x5c,
+                    0x92, 0xda, 0x6b, 0x84, 0xfe, 0xe4, 0xc6, 0x0d, 0x34, 0x78, 0x65, 0xac, 0xf4,
+                    0x7e, 0x50, 0x45, 0x38, 0xf5, 0xef, 0x8b, 0x04, 0x70, 0x20, 0x80, 0xe6, 0x09,
+                    0x1c, 0xda, 0x57, 0x97, 0xcd, 0x7d, 0x23, 0x5a, 0x54, 0x6e, 0xb1, 0x0f, 0x55,
+                    0x08, 0xdd, 0xba, 0xc2, 0xa4, 0x93, 0xf5, 0x3c, 0x3b, 0x09, 0x33, 0xd9, 0x13,
+                    0xde, 0xf8, 0x88, 0x48, 0x65, 0x4c, 0x08, 0x7c, 0x12, 0x60, 0x9d, 0xf0, 0x1b,
+                    0xaf, 0x94, 0x05, 0xce, 0x78, 0x04, 0xfd, 0x60, 0x2c, 0xd3, 0x17, 0xb7, 0xce,
+                    0xa1, 0x1e, 0x8c, 0xc7, 0xae, 0x2e, 0xa4, 0x05, 0xb4, 0x0d, 0x46, 0xb1, 0x59,
+                    0x2a, 0x30, 0xf0, 0xcb, 0x6e, 0x8c, 0x4f, 0x17, 0xd7, 0xf7, 0xc4, 0x7f, 0xeb,
+                ],
+                xfvk: [
+                    0x02, 0xdb, 0x99, 0x9e, 0x07, 0x02, 0x00, 0x00, 0x80, 0x97, 0xce, 0x15, 0xf4,
+                    0xed, 0x1b, 0x97, 0x39, 0xb0, 0x26, 0x2a, 0x46, 0x3b, 0xcb, 0x3d, 0xc9, 0xb3,
+                    0xbd, 0x23, 0x23, 0xa9, 0xba, 0xa4, 0x41, 0xca, 0x42, 0x77, 0x73, 0x83, 0xa8,
+                    0xd4, 0x35, 0xa6, 0xc5, 0x92, 0x5a, 0x0f, 0x85, 0xfa, 0x4f, 0x1e, 0x40, 0x5e,
+                    0x3a, 0x49, 0x70, 0xd0, 0xc4, 0xa4, 0xb4, 0x81, 0x44, 0x38, 0xf4, 0xe9, 0xd4,
+                    0x52, 0x0e, 0x20, 0xf7, 0xfd, 0xcf, 0x38, 0x41, 0xf9, 0x12, 0x87, 0xc0, 0x6e,
+                    0x30, 0xb6, 0x5e, 0xa1, 0xbd, 0xb7, 0x16, 0xb2, 0x31, 0xde, 0x67, 0x78, 0xa5,
+                    0xd8, 0x0e, 0xe5, 0xcd, 0x9c, 0x06, 0x0d, 0x1a, 0xba, 0xca, 0xe0, 0xaa, 0xe2,
+                    0x3b, 0xdd, 0xba, 0xc2, 0xa4, 0x93, 0xf5, 0x3c, 0x3b, 0x09, 0x33, 0xd9, 0x13,
+                    0xde, 0xf8, 0x88, 0x48, 0x65, 0x4c, 0x08, 0x7c, 0x12, 0x60, 0x9d, 0xf0, 0x1b,
+                    0xaf, 0x94, 0x05, 0xce, 0x78, 0x04, 0xfd, 0x60, 0x2c, 0xd3, 0x17, 0xb7, 0xce,
+                    0xa1, 0x1e, 0x8c, 0xc7, 0xae, 0x2e, 0xa4, 0x05, 0xb4, 0x0d, 0x46, 0xb1, 0x59,
+                    0x2a, 0x30, 0xf0, 0xcb, 0x6e, 0x8c, 0x4f, 0x17, 0xd7, 0xf7, 0xc4, 0x7f, 0xeb,
+                ],
+            },
+        ];
+
+        let seed = [
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+            24, 25, 26, 27, 28, 29, 30, 31,
+        ];
+
+        let i1 = ChildIndex::NonHardened(1);
+        let i2h = ChildIndex::Hardened(2);
+
+        let m = ExtendedSpendingKey::master(&seed);
+        let m_1 = m.derive_child(i1);
+        let m_1_2h = ExtendedSpendingKey::from_path(&m, &[i1, i2h]);
+        let xsks = [m, m_1, m_1_2h];
+
+        for (xsk, tv) in xsks.iter().zip(test_vectors.iter()) {
+            let xsk_int = xsk.derive_internal();
+            let xfvk_int = ExtendedFullViewingKey::from(xsk).derive_internal();
+
+            let mut buf = [0; 32];
+            xsk_int
+                .expsk
+                .nsk
+                .into_repr()
+                .write_le(&mut buf[..])
+                .unwrap();
+            assert_eq!(buf, tv.nsk);
+            assert_eq!(xsk_int.expsk.ovk.0, tv.ovk);
+            assert_eq!(xsk_int.dk.0, tv.dk);
+
+            xfvk_int.fvk.vk.nk.write(&mut buf[..]).unwrap();
+            assert_eq!(buf, tv.nk);
+            assert_eq!(xfvk_int.fvk.ovk.0, tv.ovk);
+            assert_eq!(xfvk_int.dk.0, tv.dk);
+
+            let mut ser = vec![];
+            xsk_int.write(&mut ser).unwrap();
+            assert_eq!(&ser[..], &tv.xsk[..]);
+
+            let mut ser = vec![];
+            xfvk_int.write(&mut ser).unwrap();
+            assert_eq!(&ser[..], &tv.xfvk[..]);
+        }
+    }
 }
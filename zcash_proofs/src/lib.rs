@@ -9,13 +9,88 @@ extern crate zcash_primitives;
 
 use bellman::groth16::{prepare_verifying_key, Parameters, PreparedVerifyingKey, VerifyingKey};
 use pairing::bls12_381::Bls12;
+use std::error;
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufReader};
+use std::io::{self, BufReader, Read};
 use std::path::Path;
 
 mod hashreader;
 pub mod sapling;
+pub mod verify;
 
+#[cfg(test)]
+mod testing;
+
+/// The BLAKE2b-512 hash of the Sapling spend parameters file, `sapling-spend.params`.
+pub const SAPLING_SPEND_HASH: &str = "8270785a1a0d0bc77196f000ee6d221c9c9894f55307bd9357c3f0105d31ca63991ab91324160d8f53e2bbd3c2633a6eb8bdf5205d822e7f3f73edac51b2b70c";
+
+/// The BLAKE2b-512 hash of the Sapling output parameters file, `sapling-output.params`.
+pub const SAPLING_OUTPUT_HASH: &str = "657e3d38dbb5cb5e7dd2970e8b03d69b4787dd907285b5a7f0790dcc8072f60bf593b32cc2d1c030e00ff5ae64bf84c5c3beb84ddc841d48264b4a171744d028";
+
+/// The BLAKE2b-512 hash of the Sprout Groth16 parameters file, `sprout-groth16.params`.
+pub const SPROUT_HASH: &str = "e9b238411bd6c0ec4791e9d04245ec350c9c5744f5610dfcce4365d5ca49dfefd5054e371842b3f88fa1b9d7e8e075249b3ebabd167fa8b0f3161292d36c180a";
+
+/// The parameter files used by Zcash.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParameterFile {
+    SaplingSpend,
+    SaplingOutput,
+    SproutGroth16,
+}
+
+impl fmt::Display for ParameterFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ParameterFile::SaplingSpend => "Sapling spend",
+            ParameterFile::SaplingOutput => "Sapling output",
+            ParameterFile::SproutGroth16 => "Sprout groth16",
+        })
+    }
+}
+
+/// Errors that can occur while loading parameter files.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file could not be read or deserialized.
+    Io(ParameterFile, io::Error),
+    /// The hash of the file did not match the expected hash.
+    ParameterMismatch {
+        file: ParameterFile,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(file, e) => write!(f, "Couldn't load {} parameters file: {}", file, e),
+            LoadError::ParameterMismatch { file, .. } => write!(
+                f,
+                "{} parameter file is not correct, please clean your `~/.zcash-params/` and re-run `fetch-params`.",
+                file
+            ),
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LoadError::Io(_, e) => Some(e),
+            LoadError::ParameterMismatch { .. } => None,
+        }
+    }
+}
+
+/// Loads the Zcash parameters from the given paths, checking them against the given
+/// hashes.
+///
+/// # Panics
+///
+/// Panics if any of the files cannot be loaded, or do not match their expected hashes.
+/// Use [`load_official_parameters`] to handle these errors instead.
 pub fn load_parameters(
     spend_path: &Path,
     spend_hash: &str,
@@ -30,56 +105,207 @@ pub fn load_parameters(
     PreparedVerifyingKey<Bls12>,
     Option<PreparedVerifyingKey<Bls12>>,
 ) {
+    // Check the Sprout hash is present whenever the Sprout path is
+    let sprout = match (sprout_path, sprout_hash) {
+        (Some(path), Some(hash)) => Some((path, Some(hash))),
+        (None, None) => None,
+        _ => panic!("Sprout groth16 parameter file is not correct, please clean your `~/.zcash-params/` and re-run `fetch-params`."),
+    };
+
+    match load_parameters_inner(
+        spend_path,
+        Some(spend_hash),
+        output_path,
+        Some(output_hash),
+        sprout,
+    ) {
+        Ok(params) => params,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+/// Loads the Zcash parameters from the given paths.
+///
+/// If `verify` is true, each file is checked against the hash of the official Zcash
+/// parameters ([`SAPLING_SPEND_HASH`], [`SAPLING_OUTPUT_HASH`], and [`SPROUT_HASH`]),
+/// and [`LoadError::ParameterMismatch`] is returned for the first file that does not
+/// match. Setting `verify` to false skips these checks, which is only useful for
+/// testing with non-standard parameters.
+pub fn load_official_parameters(
+    spend_path: &Path,
+    output_path: &Path,
+    sprout_path: Option<&Path>,
+    verify: bool,
+) -> Result<
+    (
+        Parameters<Bls12>,
+        PreparedVerifyingKey<Bls12>,
+        Parameters<Bls12>,
+        PreparedVerifyingKey<Bls12>,
+        Option<PreparedVerifyingKey<Bls12>>,
+    ),
+    LoadError,
+> {
+    let hash = |h| if verify { Some(h) } else { None };
+
+    load_parameters_inner(
+        spend_path,
+        hash(SAPLING_SPEND_HASH),
+        output_path,
+        hash(SAPLING_OUTPUT_HASH),
+        sprout_path.map(|p| (p, hash(SPROUT_HASH))),
+    )
+}
+
+/// The prepared Groth16 verifying keys for the Zcash circuits.
+///
+/// These are all that is needed to verify proofs with [`SaplingVerificationContext`],
+/// and are much smaller than the full proving parameters.
+///
+/// [`SaplingVerificationContext`]: sapling::SaplingVerificationContext
+pub struct VerifyingKeys {
+    pub spend_vk: PreparedVerifyingKey<Bls12>,
+    pub output_vk: PreparedVerifyingKey<Bls12>,
+    pub sprout_vk: Option<PreparedVerifyingKey<Bls12>>,
+}
+
+/// Loads only the Groth16 verifying keys from the given parameter files.
+///
+/// If `verify` is true, each file is checked against the hash of the official Zcash
+/// parameters, as in [`load_official_parameters`]. A substituted verifying key would
+/// allow forged proofs to verify, so the whole file is read and hashed, although only
+/// the verifying key at its beginning is deserialized. Setting `verify` to false skips
+/// these checks, which is only useful for testing with non-standard parameters.
+pub fn load_verifying_keys(
+    spend_path: &Path,
+    output_path: &Path,
+    sprout_path: Option<&Path>,
+    verify: bool,
+) -> Result<VerifyingKeys, LoadError> {
+    let hash = |h| if verify { Some(h) } else { None };
+
+    Ok(VerifyingKeys {
+        spend_vk: read_vk(
+            spend_path,
+            ParameterFile::SaplingSpend,
+            hash(SAPLING_SPEND_HASH),
+        )?,
+        output_vk: read_vk(
+            output_path,
+            ParameterFile::SaplingOutput,
+            hash(SAPLING_OUTPUT_HASH),
+        )?,
+        sprout_vk: match sprout_path {
+            Some(path) => Some(read_vk(
+                path,
+                ParameterFile::SproutGroth16,
+                hash(SPROUT_HASH),
+            )?),
+            None => None,
+        },
+    })
+}
+
+fn read_vk(
+    path: &Path,
+    file: ParameterFile,
+    expected: Option<&str>,
+) -> Result<PreparedVerifyingKey<Bls12>, LoadError> {
+    let io_err = |e| LoadError::Io(file, e);
+
+    let fs = File::open(path).map_err(io_err)?;
+    let mut fs = hashreader::HashReader::new(BufReader::with_capacity(1024 * 1024, fs));
+    let vk = VerifyingKey::<Bls12>::read(&mut fs).map_err(io_err)?;
+
+    if expected.is_some() {
+        io::copy(&mut fs, &mut io::sink()).map_err(io_err)?;
+        check_hash(file, fs, expected)?;
+    }
+
+    Ok(prepare_verifying_key(&vk))
+}
+
+fn check_hash<R: Read>(
+    file: ParameterFile,
+    fs: hashreader::HashReader<R>,
+    expected: Option<&str>,
+) -> Result<(), LoadError> {
+    let actual = fs.into_hash();
+    match expected {
+        Some(expected) if expected != actual => Err(LoadError::ParameterMismatch {
+            file,
+            expected: expected.to_owned(),
+            actual,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn load_parameters_inner(
+    spend_path: &Path,
+    spend_hash: Option<&str>,
+    output_path: &Path,
+    output_hash: Option<&str>,
+    sprout: Option<(&Path, Option<&str>)>,
+) -> Result<
+    (
+        Parameters<Bls12>,
+        PreparedVerifyingKey<Bls12>,
+        Parameters<Bls12>,
+        PreparedVerifyingKey<Bls12>,
+        Option<PreparedVerifyingKey<Bls12>>,
+    ),
+    LoadError,
+> {
+    let spend_err = |e| LoadError::Io(ParameterFile::SaplingSpend, e);
+    let output_err = |e| LoadError::Io(ParameterFile::SaplingOutput, e);
+    let sprout_err = |e| LoadError::Io(ParameterFile::SproutGroth16, e);
+
     // Load from each of the paths
-    let spend_fs = File::open(spend_path).expect("couldn't load Sapling spend parameters file");
-    let output_fs = File::open(output_path).expect("couldn't load Sapling output parameters file");
-    let sprout_fs =
-        sprout_path.map(|p| File::open(p).expect("couldn't load Sprout groth16 parameters file"));
+    let spend_fs = File::open(spend_path).map_err(spend_err)?;
+    let output_fs = File::open(output_path).map_err(output_err)?;
+    let sprout_fs = match sprout {
+        Some((path, hash)) => Some((File::open(path).map_err(sprout_err)?, hash)),
+        None => None,
+    };
 
     let mut spend_fs = hashreader::HashReader::new(BufReader::with_capacity(1024 * 1024, spend_fs));
     let mut output_fs =
         hashreader::HashReader::new(BufReader::with_capacity(1024 * 1024, output_fs));
-    let mut sprout_fs =
-        sprout_fs.map(|fs| hashreader::HashReader::new(BufReader::with_capacity(1024 * 1024, fs)));
+    let mut sprout_fs = sprout_fs.map(|(fs, hash)| {
+        (
+            hashreader::HashReader::new(BufReader::with_capacity(1024 * 1024, fs)),
+            hash,
+        )
+    });
 
     // Deserialize params
-    let spend_params = Parameters::<Bls12>::read(&mut spend_fs, false)
-        .expect("couldn't deserialize Sapling spend parameters file");
-    let output_params = Parameters::<Bls12>::read(&mut output_fs, false)
-        .expect("couldn't deserialize Sapling spend parameters file");
+    let spend_params = Parameters::<Bls12>::read(&mut spend_fs, false).map_err(spend_err)?;
+    let output_params = Parameters::<Bls12>::read(&mut output_fs, false).map_err(output_err)?;
 
     // We only deserialize the verifying key for the Sprout parameters, which
     // appears at the beginning of the parameter file. The rest is loaded
     // during proving time.
-    let sprout_vk = sprout_fs.as_mut().map(|mut fs| {
-        VerifyingKey::<Bls12>::read(&mut fs)
-            .expect("couldn't deserialize Sprout Groth16 verifying key")
-    });
+    let sprout_vk = match sprout_fs.as_mut() {
+        Some((fs, _)) => Some(VerifyingKey::<Bls12>::read(fs).map_err(sprout_err)?),
+        None => None,
+    };
 
     // There is extra stuff (the transcript) at the end of the parameter file which is
     // used to verify the parameter validity, but we're not interested in that. We do
     // want to read it, though, so that the BLAKE2b computed afterward is consistent
     // with `b2sum` on the files.
     let mut sink = io::sink();
-    io::copy(&mut spend_fs, &mut sink)
-        .expect("couldn't finish reading Sapling spend parameter file");
-    io::copy(&mut output_fs, &mut sink)
-        .expect("couldn't finish reading Sapling output parameter file");
-    if let Some(mut sprout_fs) = sprout_fs.as_mut() {
-        io::copy(&mut sprout_fs, &mut sink)
-            .expect("couldn't finish reading Sprout groth16 parameter file");
-    }
-
-    if spend_fs.into_hash() != spend_hash {
-        panic!("Sapling spend parameter file is not correct, please clean your `~/.zcash-params/` and re-run `fetch-params`.");
-    }
-
-    if output_fs.into_hash() != output_hash {
-        panic!("Sapling output parameter file is not correct, please clean your `~/.zcash-params/` and re-run `fetch-params`.");
+    io::copy(&mut spend_fs, &mut sink).map_err(spend_err)?;
+    io::copy(&mut output_fs, &mut sink).map_err(output_err)?;
+    if let Some((fs, _)) = sprout_fs.as_mut() {
+        io::copy(fs, &mut sink).map_err(sprout_err)?;
     }
 
-    if sprout_fs.map(|fs| fs.into_hash()) != sprout_hash.map(|h| h.to_owned()) {
-        panic!("Sprout groth16 parameter file is not correct, please clean your `~/.zcash-params/` and re-run `fetch-params`.");
+    check_hash(ParameterFile::SaplingSpend, spend_fs, spend_hash)?;
+    check_hash(ParameterFile::SaplingOutput, output_fs, output_hash)?;
+    if let Some((fs, hash)) = sprout_fs {
+        check_hash(ParameterFile::SproutGroth16, fs, hash)?;
     }
 
     // Prepare verifying keys
@@ -87,5 +313,106 @@ pub fn load_parameters(
     let output_vk = prepare_verifying_key(&output_params.vk);
     let sprout_vk = sprout_vk.map(|vk| prepare_verifying_key(&vk));
 
-    (spend_params, spend_vk, output_params, output_vk, sprout_vk)
+    Ok((spend_params, spend_vk, output_params, output_vk, sprout_vk))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{load_official_parameters, load_verifying_keys, LoadError, ParameterFile};
+    use crate::testing::{temp_path, write_params};
+
+    #[test]
+    fn load_official_parameters_missing_file() {
+        let spend = write_params("missing-spend.params", None);
+        let missing = temp_path("missing-output.params");
+
+        match load_official_parameters(&spend, &missing, None, false) {
+            Err(LoadError::Io(ParameterFile::SaplingOutput, _)) => (),
+            Err(e) => panic!("Unexpected error: {}", e),
+            Ok(_) => panic!("Loaded a missing file"),
+        }
+
+        match load_official_parameters(&spend, &spend, Some(&missing), false) {
+            Err(LoadError::Io(ParameterFile::SproutGroth16, _)) => (),
+            Err(e) => panic!("Unexpected error: {}", e),
+            Ok(_) => panic!("Loaded a missing file"),
+        }
+
+        fs::remove_file(spend).unwrap();
+    }
+
+    #[test]
+    fn load_official_parameters_hash_mismatch() {
+        let spend = write_params("mismatch-spend.params", None);
+        let truncated = write_params("mismatch-truncated.params", Some(100));
+
+        // The parameters can be deserialized, but are not the official ones.
+        match load_official_parameters(&spend, &spend, None, true) {
+            Err(LoadError::ParameterMismatch {
+                file,
+                expected,
+                actual,
+            }) => {
+                assert_eq!(file, ParameterFile::SaplingSpend);
+                assert_eq!(expected, super::SAPLING_SPEND_HASH);
+                assert_ne!(actual, expected);
+            }
+            Err(e) => panic!("Unexpected error: {}", e),
+            Ok(_) => panic!("Loaded non-official parameters"),
+        }
+        assert!(load_official_parameters(&spend, &spend, None, false).is_ok());
+
+        // A truncated file cannot be deserialized, so the hash is never checked.
+        match load_official_parameters(&spend, &truncated, None, true) {
+            Err(LoadError::Io(ParameterFile::SaplingOutput, _)) => (),
+            Err(e) => panic!("Unexpected error: {}", e),
+            Ok(_) => panic!("Loaded a truncated file"),
+        }
+
+        fs::remove_file(spend).unwrap();
+        fs::remove_file(truncated).unwrap();
+    }
+
+    #[test]
+    fn load_verifying_keys_missing_file() {
+        let spend = write_params("vk-spend.params", None);
+        let missing = temp_path("vk-missing.params");
+
+        match load_verifying_keys(&missing, &spend, None, false) {
+            Err(LoadError::Io(ParameterFile::SaplingSpend, _)) => (),
+            Err(e) => panic!("Unexpected error: {}", e),
+            Ok(_) => panic!("Loaded a missing file"),
+        }
+
+        match load_verifying_keys(&spend, &spend, Some(&missing), false) {
+            Err(LoadError::Io(ParameterFile::SproutGroth16, _)) => (),
+            Err(e) => panic!("Unexpected error: {}", e),
+            Ok(_) => panic!("Loaded a missing file"),
+        }
+
+        let vks = load_verifying_keys(&spend, &spend, Some(&spend), false).unwrap();
+        assert!(vks.sprout_vk.is_some());
+
+        fs::remove_file(spend).unwrap();
+    }
+
+    #[test]
+    fn load_verifying_keys_hash_mismatch() {
+        let spend = write_params("vk-mismatch.params", None);
+
+        // The verifying key can be deserialized, but is not the official one.
+        match load_verifying_keys(&spend, &spend, None, true) {
+            Err(LoadError::ParameterMismatch { file, expected, .. }) => {
+                assert_eq!(file, ParameterFile::SaplingSpend);
+                assert_eq!(expected, super::SAPLING_SPEND_HASH);
+            }
+            Err(e) => panic!("Unexpected error: {}", e),
+            Ok(_) => panic!("Loaded a non-official verifying key"),
+        }
+        assert!(load_verifying_keys(&spend, &spend, None, false).is_ok());
+
+        fs::remove_file(spend).unwrap();
+    }
 }
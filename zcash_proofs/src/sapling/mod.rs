@@ -4,9 +4,13 @@ use sapling_crypto::jubjub::{
 };
 
 mod prover;
+mod session;
 mod verifier;
 
 pub use self::prover::SaplingProvingContext;
+pub use self::session::{
+    CancelToken, Error as ProvingError, OutputProof, ProvingSession, SaplingProofs, SpendProof,
+};
 pub use self::verifier::SaplingVerificationContext;
 
 // This function computes `value` in the exponent of the value commitment base
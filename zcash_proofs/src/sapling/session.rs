@@ -0,0 +1,309 @@
+use bellman::groth16::{Parameters, PreparedVerifyingKey, Proof};
+use pairing::bls12_381::{Bls12, Fr};
+use sapling_crypto::{
+    jubjub::{edwards, fs::Fs, JubjubBls12, Unknown},
+    primitives::{Diversifier, PaymentAddress, ProofGenerationKey},
+    redjubjub::PublicKey,
+};
+use std::error;
+use std::fmt;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use zcash_primitives::{merkle_tree::CommitmentTreeWitness, sapling::Node};
+
+use super::SaplingProvingContext;
+
+/// A handle that can be used to cancel a [`ProvingSession`] from another thread.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Requests that any session using this token stops creating proofs.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Errors that can occur while creating the proofs in a [`ProvingSession`].
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The session was cancelled before all of its proofs were created.
+    Cancelled,
+    /// The spend with the given index could not be proven, because its diversifier is
+    /// invalid or its witness does not match its anchor.
+    InvalidSpend(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Cancelled => write!(f, "Proof creation was cancelled"),
+            Error::InvalidSpend(i) => write!(f, "Could not create a proof for spend {}", i),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+struct SpendInfo {
+    proof_generation_key: ProofGenerationKey<Bls12>,
+    diversifier: Diversifier,
+    rcm: Fs,
+    ar: Fs,
+    value: u64,
+    anchor: Fr,
+    witness: CommitmentTreeWitness<Node>,
+}
+
+struct OutputInfo {
+    esk: Fs,
+    payment_address: PaymentAddress<Bls12>,
+    rcm: Fs,
+    value: u64,
+}
+
+/// The proof, value commitment, and re-randomized key for a Sapling SpendDescription.
+pub struct SpendProof {
+    pub proof: Proof<Bls12>,
+    pub cv: edwards::Point<Bls12, Unknown>,
+    pub rk: PublicKey<Bls12>,
+}
+
+/// The proof and value commitment for a Sapling OutputDescription.
+pub struct OutputProof {
+    pub proof: Proof<Bls12>,
+    pub cv: edwards::Point<Bls12, Unknown>,
+}
+
+/// The result of a completed [`ProvingSession`].
+pub struct SaplingProofs {
+    /// The context in which the proofs were created, from which the bindingSig can be
+    /// created.
+    pub context: SaplingProvingContext,
+    pub spends: Vec<SpendProof>,
+    pub outputs: Vec<OutputProof>,
+}
+
+/// A set of Sapling proofs to be created for a single transaction, with support for
+/// reporting progress and for cancellation.
+///
+/// The session itself is single-threaded: [`ProvingSession::prove`] runs on the calling
+/// thread and creates the proofs one at a time, and the parallelism comes from the prover
+/// using all available threads for each proof. To prove in the background, move the
+/// session (and the parameters it borrows) to another thread, and cancel it from the
+/// original thread with a [`CancelToken`].
+///
+/// Cancellation is checked between proofs, so a proof that is in progress when the
+/// session is cancelled will be completed and then discarded.
+pub struct ProvingSession<'a> {
+    spend_params: &'a Parameters<Bls12>,
+    spend_vk: &'a PreparedVerifyingKey<Bls12>,
+    output_params: &'a Parameters<Bls12>,
+    params: &'a JubjubBls12,
+    spends: Vec<SpendInfo>,
+    outputs: Vec<OutputInfo>,
+}
+
+impl<'a> ProvingSession<'a> {
+    pub fn new(
+        spend_params: &'a Parameters<Bls12>,
+        spend_vk: &'a PreparedVerifyingKey<Bls12>,
+        output_params: &'a Parameters<Bls12>,
+        params: &'a JubjubBls12,
+    ) -> Self {
+        ProvingSession {
+            spend_params,
+            spend_vk,
+            output_params,
+            params,
+            spends: vec![],
+            outputs: vec![],
+        }
+    }
+
+    /// Adds a Sapling spend to be proven. See [`SaplingProvingContext::spend_proof`].
+    pub fn add_spend(
+        &mut self,
+        proof_generation_key: ProofGenerationKey<Bls12>,
+        diversifier: Diversifier,
+        rcm: Fs,
+        ar: Fs,
+        value: u64,
+        anchor: Fr,
+        witness: CommitmentTreeWitness<Node>,
+    ) {
+        self.spends.push(SpendInfo {
+            proof_generation_key,
+            diversifier,
+            rcm,
+            ar,
+            value,
+            anchor,
+            witness,
+        });
+    }
+
+    /// Adds a Sapling output to be proven. See [`SaplingProvingContext::output_proof`].
+    pub fn add_output(
+        &mut self,
+        esk: Fs,
+        payment_address: PaymentAddress<Bls12>,
+        rcm: Fs,
+        value: u64,
+    ) {
+        self.outputs.push(OutputInfo {
+            esk,
+            payment_address,
+            rcm,
+            value,
+        });
+    }
+
+    /// Returns the number of proofs that this session will create.
+    pub fn proof_count(&self) -> usize {
+        self.spends.len() + self.outputs.len()
+    }
+
+    /// Creates all of the proofs in this session, spends first.
+    ///
+    /// `progress` is called with `(completed, total)` after each proof is created, and
+    /// the session stops with [`Error::Cancelled`] as soon as `cancel` is triggered.
+    pub fn prove<F>(self, cancel: &CancelToken, mut progress: F) -> Result<SaplingProofs, Error>
+    where
+        F: FnMut(usize, usize),
+    {
+        let total = self.proof_count();
+        let mut context = SaplingProvingContext::new();
+
+        let mut spends = Vec::with_capacity(self.spends.len());
+        for (i, spend) in self.spends.into_iter().enumerate() {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+
+            let (proof, cv, rk) = context
+                .spend_proof(
+                    spend.proof_generation_key,
+                    spend.diversifier,
+                    spend.rcm,
+                    spend.ar,
+                    spend.value,
+                    spend.anchor,
+                    spend.witness,
+                    self.spend_params,
+                    self.spend_vk,
+                    self.params,
+                )
+                .map_err(|()| Error::InvalidSpend(i))?;
+            spends.push(SpendProof { proof, cv, rk });
+
+            progress(spends.len(), total);
+        }
+
+        let mut outputs = Vec::with_capacity(self.outputs.len());
+        for output in self.outputs {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+
+            let (proof, cv) = context.output_proof(
+                output.esk,
+                output.payment_address,
+                output.rcm,
+                output.value,
+                self.output_params,
+                self.params,
+            );
+            outputs.push(OutputProof { proof, cv });
+
+            progress(spends.len() + outputs.len(), total);
+        }
+
+        Ok(SaplingProofs {
+            context,
+            spends,
+            outputs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bellman::groth16::prepare_verifying_key;
+    use ff::Field;
+    use pairing::bls12_381::Bls12;
+    use rand_os::OsRng;
+    use sapling_crypto::{
+        jubjub::{fs::Fs, JubjubBls12},
+        primitives::{Diversifier, PaymentAddress},
+    };
+
+    use super::{CancelToken, Error, ProvingSession};
+    use crate::testing::{dummy_params, output_params};
+
+    fn add_output(session: &mut ProvingSession, params: &JubjubBls12) {
+        let mut rng = OsRng;
+        let diversifier = Diversifier([0; 11]);
+        let pk_d = diversifier
+            .g_d::<Bls12>(params)
+            .unwrap()
+            .mul(Fs::random(&mut rng), params);
+        let payment_address = PaymentAddress { pk_d, diversifier };
+        session.add_output(
+            Fs::random(&mut rng),
+            payment_address,
+            Fs::random(&mut rng),
+            1000,
+        );
+    }
+
+    #[test]
+    fn cancel_before_prove() {
+        let params = JubjubBls12::new();
+        let dummy_params = dummy_params();
+        let vk = prepare_verifying_key(&dummy_params.vk);
+
+        let mut session = ProvingSession::new(&dummy_params, &vk, &dummy_params, &params);
+        add_output(&mut session, &params);
+
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let mut progress = vec![];
+        let res = session.prove(&cancel, |done, total| progress.push((done, total)));
+
+        assert_eq!(res.err(), Some(Error::Cancelled));
+        assert!(progress.is_empty());
+    }
+
+    #[test]
+    fn cancel_during_session() {
+        let params = JubjubBls12::new();
+        let output_params = output_params(&params);
+        let vk = prepare_verifying_key(&output_params.vk);
+
+        let mut session = ProvingSession::new(&output_params, &vk, &output_params, &params);
+        add_output(&mut session, &params);
+        add_output(&mut session, &params);
+        assert_eq!(session.proof_count(), 2);
+
+        let cancel = CancelToken::new();
+        let mut progress = vec![];
+        let res = session.prove(&cancel, |done, total| {
+            progress.push((done, total));
+            cancel.cancel();
+        });
+
+        assert_eq!(res.err(), Some(Error::Cancelled));
+        assert_eq!(progress, vec![(1, 2)]);
+    }
+}
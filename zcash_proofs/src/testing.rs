@@ -0,0 +1,85 @@
+//! Helpers shared by the tests in this crate.
+
+use bellman::{
+    groth16::{generate_random_parameters, Parameters},
+    Circuit, ConstraintSystem, SynthesisError,
+};
+use ff::Field;
+use pairing::bls12_381::{Bls12, Fr};
+use rand_os::OsRng;
+use sapling_crypto::{
+    circuit::sapling::{Output, Spend, TREE_DEPTH},
+    jubjub::JubjubBls12,
+};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+/// A circuit with a single constraint, for creating small (non-official) parameters.
+pub(crate) struct Dummy;
+
+impl Circuit<Bls12> for Dummy {
+    fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let a = cs.alloc(|| "a", || Ok(Fr::one()))?;
+        cs.enforce(|| "a * a = a", |lc| lc + a, |lc| lc + a, |lc| lc + a);
+        Ok(())
+    }
+}
+
+/// Returns parameters for the [`Dummy`] circuit, for tests that never create a proof.
+pub(crate) fn dummy_params() -> Parameters<Bls12> {
+    generate_random_parameters(Dummy, &mut OsRng).unwrap()
+}
+
+/// Returns parameters for the Spend circuit with random toxic waste.
+///
+/// These are sufficient for creating spend proofs, which only verify against the
+/// verifying key of the same parameters.
+pub(crate) fn spend_params(params: &JubjubBls12) -> Parameters<Bls12> {
+    let circuit = Spend {
+        params,
+        value_commitment: None,
+        proof_generation_key: None,
+        payment_address: None,
+        commitment_randomness: None,
+        ar: None,
+        auth_path: vec![None; TREE_DEPTH],
+        anchor: None,
+    };
+    generate_random_parameters(circuit, &mut OsRng).unwrap()
+}
+
+/// Returns parameters for the Output circuit with random toxic waste.
+///
+/// These are sufficient for creating output proofs, which only verify against the
+/// verifying key of the same parameters.
+pub(crate) fn output_params(params: &JubjubBls12) -> Parameters<Bls12> {
+    let circuit = Output {
+        params,
+        value_commitment: None,
+        payment_address: None,
+        commitment_randomness: None,
+        esk: None,
+    };
+    generate_random_parameters(circuit, &mut OsRng).unwrap()
+}
+
+/// Returns a path in the temporary directory that is unique to this test process.
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("zcash_proofs-{}-{}", process::id(), name))
+}
+
+/// Writes a parameters file for the [`Dummy`] circuit, optionally truncated to `len`
+/// bytes.
+pub(crate) fn write_params(name: &str, len: Option<usize>) -> PathBuf {
+    let mut data = vec![];
+    dummy_params().write(&mut data).unwrap();
+    if let Some(len) = len {
+        data.truncate(len);
+    }
+
+    let path = temp_path(name);
+    fs::write(&path, data).unwrap();
+    path
+}
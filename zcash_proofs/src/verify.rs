@@ -0,0 +1,576 @@
+//! Consensus checks on complete transactions.
+
+use bellman::groth16::Proof;
+use pairing::bls12_381::Bls12;
+use sapling_crypto::{jubjub::JubjubBls12, redjubjub::PublicKey};
+use std::collections::HashSet;
+use std::error;
+use std::fmt;
+use zcash_primitives::transaction::{
+    components::MAX_MONEY, signature_hash, Transaction, SIGHASH_ALL,
+};
+
+use crate::{sapling::SaplingVerificationContext, VerifyingKeys};
+
+/// Errors that can occur while verifying a transaction.
+#[derive(Debug, PartialEq)]
+pub enum VerifyError {
+    /// The sum of the transparent outputs, or of the JoinSplit public values, is out
+    /// of range.
+    ValueOutOfRange,
+    /// The Sapling value balance is non-zero in a transaction without Sapling spends
+    /// or outputs.
+    ValueBalance,
+    /// The spend with the given index reveals a nullifier that is also revealed by an
+    /// earlier spend in the transaction.
+    DuplicateNullifier(usize),
+    /// The JoinSplit with the given index reveals a nullifier that is also revealed
+    /// earlier in the transaction.
+    DuplicateSproutNullifier(usize),
+    /// The spend with the given index has an invalid proof or signature.
+    InvalidSpend(usize),
+    /// The output with the given index has an invalid proof.
+    InvalidOutput(usize),
+    /// The binding signature is missing or invalid.
+    InvalidBindingSig,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::ValueOutOfRange => write!(f, "Transaction values out of range"),
+            VerifyError::ValueBalance => {
+                write!(f, "Non-zero value balance without Sapling components")
+            }
+            VerifyError::DuplicateNullifier(i) => write!(f, "Duplicate nullifier in spend {}", i),
+            VerifyError::DuplicateSproutNullifier(i) => {
+                write!(f, "Duplicate nullifier in JoinSplit {}", i)
+            }
+            VerifyError::InvalidSpend(i) => write!(f, "Invalid Sapling spend {}", i),
+            VerifyError::InvalidOutput(i) => write!(f, "Invalid Sapling output {}", i),
+            VerifyError::InvalidBindingSig => write!(f, "Invalid binding signature"),
+        }
+    }
+}
+
+impl error::Error for VerifyError {}
+
+fn checked_sum<I: Iterator<Item = i64>>(values: I) -> Option<i64> {
+    let mut total = 0i64;
+    for value in values {
+        total = total.checked_add(value)?;
+        if total > MAX_MONEY {
+            return None;
+        }
+    }
+    Some(total)
+}
+
+/// Checks that the transparent, Sprout and Sapling values of the transaction are each in
+/// range, and that the total values entering and leaving the transparent value pool are
+/// in range.
+fn check_values(tx: &Transaction) -> Result<(), VerifyError> {
+    let value_balance = tx.value_balance.0;
+    if value_balance != 0 && tx.shielded_spends.is_empty() && tx.shielded_outputs.is_empty() {
+        return Err(VerifyError::ValueBalance);
+    }
+
+    checked_sum(tx.vout.iter().map(|out| out.value.0)).ok_or(VerifyError::ValueOutOfRange)?;
+    checked_sum(tx.joinsplits.iter().map(|js| js.vpub_old.0))
+        .ok_or(VerifyError::ValueOutOfRange)?;
+    checked_sum(tx.joinsplits.iter().map(|js| js.vpub_new.0))
+        .ok_or(VerifyError::ValueOutOfRange)?;
+
+    // A negative value balance moves value into the Sapling pool, and a positive one
+    // moves value out of it.
+    checked_sum(
+        tx.vout
+            .iter()
+            .map(|out| out.value.0)
+            .chain(tx.joinsplits.iter().map(|js| js.vpub_old.0))
+            .chain(Some(std::cmp::max(-value_balance, 0))),
+    )
+    .ok_or(VerifyError::ValueOutOfRange)?;
+    checked_sum(
+        tx.joinsplits
+            .iter()
+            .map(|js| js.vpub_new.0)
+            .chain(Some(std::cmp::max(value_balance, 0))),
+    )
+    .ok_or(VerifyError::ValueOutOfRange)?;
+
+    Ok(())
+}
+
+/// Checks that no Sprout or Sapling nullifier is revealed twice within the transaction.
+fn check_nullifiers(tx: &Transaction) -> Result<(), VerifyError> {
+    let mut sprout_nullifiers = HashSet::new();
+    for (i, js) in tx.joinsplits.iter().enumerate() {
+        for nf in js.nullifiers.iter() {
+            if !sprout_nullifiers.insert(*nf) {
+                return Err(VerifyError::DuplicateSproutNullifier(i));
+            }
+        }
+    }
+
+    let mut sapling_nullifiers = HashSet::new();
+    for (i, spend) in tx.shielded_spends.iter().enumerate() {
+        if !sapling_nullifiers.insert(spend.nullifier) {
+            return Err(VerifyError::DuplicateNullifier(i));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the context-free consensus rules for the transparent values and Sapling
+/// components of the given transaction.
+///
+/// This checks that values are in range, that no Sprout or Sapling nullifier is revealed
+/// twice, and the validity of all Sapling proofs and signatures. Transparent scripts, JoinSplit proofs
+/// and signatures, and any rules that depend on the chain state (such as anchors and
+/// nullifiers being unspent) are not checked.
+pub fn verify_transaction(
+    tx: &Transaction,
+    consensus_branch_id: u32,
+    keys: &VerifyingKeys,
+    params: &JubjubBls12,
+) -> Result<(), VerifyError> {
+    check_values(tx)?;
+    check_nullifiers(tx)?;
+
+    if tx.shielded_spends.is_empty() && tx.shielded_outputs.is_empty() {
+        return Ok(());
+    }
+
+    let mut sighash = [0u8; 32];
+    sighash.copy_from_slice(&signature_hash(tx, consensus_branch_id, SIGHASH_ALL, None));
+
+    let mut ctx = SaplingVerificationContext::new();
+
+    for (i, spend) in tx.shielded_spends.iter().enumerate() {
+        let zkproof =
+            Proof::<Bls12>::read(&spend.zkproof[..]).map_err(|_| VerifyError::InvalidSpend(i))?;
+        let spend_auth_sig = spend.spend_auth_sig.ok_or(VerifyError::InvalidSpend(i))?;
+
+        if !ctx.check_spend(
+            spend.cv.clone(),
+            spend.anchor,
+            &spend.nullifier,
+            PublicKey(spend.rk.0.clone()),
+            &sighash,
+            spend_auth_sig,
+            zkproof,
+            &keys.spend_vk,
+            params,
+        ) {
+            return Err(VerifyError::InvalidSpend(i));
+        }
+    }
+
+    for (i, output) in tx.shielded_outputs.iter().enumerate() {
+        let zkproof =
+            Proof::<Bls12>::read(&output.zkproof[..]).map_err(|_| VerifyError::InvalidOutput(i))?;
+
+        if !ctx.check_output(
+            output.cv.clone(),
+            output.cmu,
+            output.ephemeral_key.clone(),
+            zkproof,
+            &keys.output_vk,
+            params,
+        ) {
+            return Err(VerifyError::InvalidOutput(i));
+        }
+    }
+
+    let binding_sig = tx.binding_sig.ok_or(VerifyError::InvalidBindingSig)?;
+    if ctx.final_check(tx.value_balance.0, &sighash, binding_sig, params) {
+        Ok(())
+    } else {
+        Err(VerifyError::InvalidBindingSig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bellman::groth16::{prepare_verifying_key, Proof};
+    use ff::{Field, PrimeField};
+    use pairing::bls12_381::{Bls12, Fr};
+    use rand_os::OsRng;
+    use sapling_crypto::{
+        jubjub::{edwards, fs::Fs, JubjubBls12, Unknown},
+        primitives::{Diversifier, PaymentAddress},
+        redjubjub::{PrivateKey, PublicKey, Signature},
+    };
+    use zcash_primitives::{
+        keys::ExpandedSpendingKey,
+        merkle_tree::{CommitmentTree, IncrementalWitness},
+        sapling::{spend_sig, Node},
+        transaction::{
+            components::{
+                Amount, JSDescription, OutputDescription, Script, SpendDescription, TxOut,
+                MAX_MONEY,
+            },
+            signature_hash_data, Transaction, TransactionData, SIGHASH_ALL,
+        },
+        JUBJUB,
+    };
+
+    use super::{check_nullifiers, check_values, verify_transaction, VerifyError};
+    use crate::{
+        sapling::SaplingProvingContext,
+        testing::{output_params, spend_params},
+        VerifyingKeys,
+    };
+
+    const SAPLING_BRANCH_ID: u32 = 0x76b8_09bb;
+
+    fn joinsplit(vpub_old: i64, vpub_new: i64, nullifiers: [[u8; 32]; 2]) -> JSDescription {
+        let mut data = vec![];
+        data.extend_from_slice(&(vpub_old as u64).to_le_bytes());
+        data.extend_from_slice(&(vpub_new as u64).to_le_bytes());
+        data.extend_from_slice(&[0; 32]);
+        data.extend_from_slice(&nullifiers[0]);
+        data.extend_from_slice(&nullifiers[1]);
+        // commitments, ephemeral key, random seed, MACs, Groth16 proof, ciphertexts
+        data.resize(data.len() + 64 + 32 + 32 + 64 + 192 + 2 * 601, 0);
+        JSDescription::read(&data[..], true).unwrap()
+    }
+
+    fn spend(nullifier: [u8; 32]) -> SpendDescription {
+        // The identity is encoded as (u = 0, v = 1).
+        let mut identity = [0; 32];
+        identity[0] = 1;
+
+        let mut data = vec![];
+        data.extend_from_slice(&identity);
+        data.extend_from_slice(&[0; 32]);
+        data.extend_from_slice(&nullifier);
+        data.extend_from_slice(&identity);
+        // zkproof, spendAuthSig
+        data.resize(data.len() + 192 + 64, 0);
+        SpendDescription::read(&mut &data[..]).unwrap()
+    }
+
+    fn freeze(mut tx: TransactionData) -> Transaction {
+        if !tx.joinsplits.is_empty() {
+            tx.joinsplit_pubkey = Some([0; 32]);
+            tx.joinsplit_sig = Some([0; 64]);
+        }
+        if !tx.shielded_spends.is_empty() {
+            tx.binding_sig = Some(Signature::read(&[0; 64][..]).unwrap());
+        }
+        tx.freeze().unwrap()
+    }
+
+    fn txout(value: i64) -> TxOut {
+        TxOut {
+            value: Amount(value),
+            script_pubkey: Script(vec![]),
+        }
+    }
+
+    #[test]
+    fn value_out_of_range() {
+        let mut tx = TransactionData::new();
+        tx.vout.push(txout(MAX_MONEY));
+        assert_eq!(check_values(&freeze(tx)), Ok(()));
+
+        let mut tx = TransactionData::new();
+        tx.vout.push(txout(MAX_MONEY));
+        tx.vout.push(txout(1));
+        assert_eq!(check_values(&freeze(tx)), Err(VerifyError::ValueOutOfRange));
+
+        // Transparent outputs and vpub_old are each in range, but not in total
+        let mut tx = TransactionData::new();
+        tx.vout.push(txout(MAX_MONEY));
+        tx.joinsplits.push(joinsplit(1, 0, [[1; 32], [2; 32]]));
+        assert_eq!(check_values(&freeze(tx)), Err(VerifyError::ValueOutOfRange));
+
+        // Transparent outputs and value moved into the Sapling pool
+        let mut tx = TransactionData::new();
+        tx.vout.push(txout(MAX_MONEY));
+        tx.shielded_spends.push(spend([1; 32]));
+        tx.value_balance = Amount(-1);
+        assert_eq!(check_values(&freeze(tx)), Err(VerifyError::ValueOutOfRange));
+
+        // vpub_new and value moved out of the Sapling pool
+        let mut tx = TransactionData::new();
+        tx.joinsplits
+            .push(joinsplit(0, MAX_MONEY, [[1; 32], [2; 32]]));
+        tx.shielded_spends.push(spend([1; 32]));
+        tx.value_balance = Amount(1);
+        assert_eq!(check_values(&freeze(tx)), Err(VerifyError::ValueOutOfRange));
+    }
+
+    #[test]
+    fn value_balance_without_sapling() {
+        let mut tx = TransactionData::new();
+        tx.value_balance = Amount(1);
+        assert_eq!(check_values(&freeze(tx)), Err(VerifyError::ValueBalance));
+    }
+
+    #[test]
+    fn duplicate_nullifiers() {
+        let mut tx = TransactionData::new();
+        tx.shielded_spends.push(spend([1; 32]));
+        tx.shielded_spends.push(spend([2; 32]));
+        tx.shielded_spends.push(spend([1; 32]));
+        assert_eq!(
+            check_nullifiers(&freeze(tx)),
+            Err(VerifyError::DuplicateNullifier(2))
+        );
+
+        let mut tx = TransactionData::new();
+        tx.joinsplits.push(joinsplit(0, 0, [[1; 32], [2; 32]]));
+        tx.joinsplits.push(joinsplit(0, 0, [[3; 32], [2; 32]]));
+        assert_eq!(
+            check_nullifiers(&freeze(tx)),
+            Err(VerifyError::DuplicateSproutNullifier(1))
+        );
+
+        let mut tx = TransactionData::new();
+        tx.joinsplits.push(joinsplit(0, 0, [[1; 32], [1; 32]]));
+        assert_eq!(
+            check_nullifiers(&freeze(tx)),
+            Err(VerifyError::DuplicateSproutNullifier(0))
+        );
+
+        // Sprout and Sapling nullifiers are in separate sets
+        let mut tx = TransactionData::new();
+        tx.joinsplits.push(joinsplit(0, 0, [[1; 32], [2; 32]]));
+        tx.shielded_spends.push(spend([1; 32]));
+        assert_eq!(check_nullifiers(&freeze(tx)), Ok(()));
+    }
+
+    /// The proofs and keys for a transaction with one Sapling spend and one Sapling
+    /// output, created with parameters generated from random toxic waste.
+    struct SaplingBundle {
+        keys: VerifyingKeys,
+        ask: PrivateKey<Bls12>,
+        ar: Fs,
+        bsk: SaplingProvingContext,
+        value_balance: i64,
+        spend_cv: edwards::Point<Bls12, Unknown>,
+        anchor: Fr,
+        nullifier: [u8; 32],
+        rk: PublicKey<Bls12>,
+        spend_proof: Proof<Bls12>,
+        output_cv: edwards::Point<Bls12, Unknown>,
+        cmu: Fr,
+        epk: edwards::Point<Bls12, Unknown>,
+        output_proof: Proof<Bls12>,
+    }
+
+    fn sapling_bundle(params: &JubjubBls12) -> SaplingBundle {
+        let mut rng = OsRng;
+        let spend_params = spend_params(params);
+        let output_params = output_params(params);
+        let spend_vk = prepare_verifying_key(&spend_params.vk);
+
+        let expsk = ExpandedSpendingKey::<Bls12>::from_spending_key(&[7; 32]);
+        let proof_generation_key = expsk.proof_generation_key(params);
+        let vk = proof_generation_key.into_viewing_key(params);
+        let (diversifier, to) = (0..)
+            .filter_map(|i| {
+                let diversifier = Diversifier([i; 11]);
+                vk.into_payment_address(diversifier, params)
+                    .map(|to| (diversifier, to))
+            })
+            .next()
+            .unwrap();
+
+        // The spent note is the only leaf in the commitment tree.
+        let rcm = Fs::random(&mut rng);
+        let note = to.create_note(1000, rcm, params).unwrap();
+        let mut tree = CommitmentTree::new();
+        tree.append(Node::new(note.cm(params).into_repr())).unwrap();
+        let witness = IncrementalWitness::from_tree(&tree).path().unwrap();
+        let anchor: Fr = tree.root().into();
+        let mut nullifier = [0; 32];
+        nullifier.copy_from_slice(&note.nf(&vk, witness.position, params));
+
+        let mut bsk = SaplingProvingContext::new();
+        let ar = Fs::random(&mut rng);
+        let (spend_proof, spend_cv, rk) = bsk
+            .spend_proof(
+                proof_generation_key,
+                diversifier,
+                rcm,
+                ar,
+                1000,
+                anchor,
+                witness,
+                &spend_params,
+                &spend_vk,
+                params,
+            )
+            .unwrap();
+
+        let recipient = PaymentAddress {
+            pk_d: to.pk_d.clone(),
+            diversifier,
+        };
+        let esk = Fs::random(&mut rng);
+        let rcm = Fs::random(&mut rng);
+        let cmu = recipient.create_note(900, rcm, params).unwrap().cm(params);
+        let epk = recipient.g_d(params).unwrap().mul(esk, params).into();
+        let (output_proof, output_cv) =
+            bsk.output_proof(esk, recipient, rcm, 900, &output_params, params);
+
+        SaplingBundle {
+            keys: VerifyingKeys {
+                spend_vk,
+                output_vk: prepare_verifying_key(&output_params.vk),
+                sprout_vk: None,
+            },
+            ask: PrivateKey(expsk.ask),
+            ar,
+            bsk,
+            value_balance: 100,
+            spend_cv,
+            anchor,
+            nullifier,
+            rk,
+            spend_proof,
+            output_cv,
+            cmu,
+            epk,
+            output_proof,
+        }
+    }
+
+    /// The ways in which a transaction built from a [`SaplingBundle`] can be tampered
+    /// with.
+    #[derive(Clone, Copy, PartialEq)]
+    enum Tamper {
+        None,
+        SpendProof,
+        OutputProof,
+        SpendAuthSig,
+        BindingSig,
+    }
+
+    /// Returns a proof that is well-formed but does not verify.
+    fn tampered(proof: &Proof<Bls12>) -> Proof<Bls12> {
+        Proof {
+            a: proof.c,
+            b: proof.b,
+            c: proof.a,
+        }
+    }
+
+    fn proof_bytes(proof: &Proof<Bls12>) -> [u8; 192] {
+        let mut zkproof = [0; 192];
+        proof.write(&mut zkproof[..]).unwrap();
+        zkproof
+    }
+
+    fn transaction(bundle: &SaplingBundle, tamper: Tamper, params: &JubjubBls12) -> Transaction {
+        let spend_proof = match tamper {
+            Tamper::SpendProof => tampered(&bundle.spend_proof),
+            _ => bundle.spend_proof.clone(),
+        };
+        let output_proof = match tamper {
+            Tamper::OutputProof => tampered(&bundle.output_proof),
+            _ => bundle.output_proof.clone(),
+        };
+
+        let mut tx = TransactionData::new();
+        tx.value_balance = Amount(bundle.value_balance);
+        tx.shielded_spends.push(SpendDescription {
+            cv: bundle.spend_cv.clone(),
+            anchor: bundle.anchor,
+            nullifier: bundle.nullifier,
+            rk: PublicKey(bundle.rk.0.clone()),
+            zkproof: proof_bytes(&spend_proof),
+            spend_auth_sig: None,
+        });
+        tx.shielded_outputs.push(OutputDescription {
+            cv: bundle.output_cv.clone(),
+            cmu: bundle.cmu,
+            ephemeral_key: bundle.epk.clone(),
+            enc_ciphertext: [0; 580],
+            out_ciphertext: [0; 80],
+            zkproof: proof_bytes(&output_proof),
+        });
+
+        let mut sighash = [0u8; 32];
+        sighash.copy_from_slice(&signature_hash_data(
+            &tx,
+            SAPLING_BRANCH_ID,
+            SIGHASH_ALL,
+            None,
+        ));
+        // A valid signature, but over a different message.
+        let sign = |t| {
+            let mut sighash = sighash;
+            if tamper == t {
+                sighash[0] ^= 1;
+            }
+            sighash
+        };
+
+        tx.shielded_spends[0].spend_auth_sig = Some(spend_sig(
+            PrivateKey(bundle.ask.0),
+            bundle.ar,
+            &sign(Tamper::SpendAuthSig),
+            params,
+        ));
+        tx.binding_sig = Some(
+            bundle
+                .bsk
+                .binding_sig(bundle.value_balance, &sign(Tamper::BindingSig), params)
+                .unwrap(),
+        );
+
+        tx.freeze().unwrap()
+    }
+
+    // Generating parameters for the Spend circuit takes too long without optimizations,
+    // so run this with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn sapling_proofs_and_signatures() {
+        let params = &JUBJUB;
+        let bundle = sapling_bundle(params);
+        let verify = |tamper| {
+            verify_transaction(
+                &transaction(&bundle, tamper, params),
+                SAPLING_BRANCH_ID,
+                &bundle.keys,
+                params,
+            )
+        };
+
+        assert_eq!(verify(Tamper::None), Ok(()));
+        assert_eq!(
+            verify(Tamper::SpendProof),
+            Err(VerifyError::InvalidSpend(0))
+        );
+        assert_eq!(
+            verify(Tamper::OutputProof),
+            Err(VerifyError::InvalidOutput(0))
+        );
+        assert_eq!(
+            verify(Tamper::SpendAuthSig),
+            Err(VerifyError::InvalidSpend(0))
+        );
+        assert_eq!(
+            verify(Tamper::BindingSig),
+            Err(VerifyError::InvalidBindingSig)
+        );
+
+        // The proofs and signatures are bound to the consensus branch.
+        assert_eq!(
+            verify_transaction(
+                &transaction(&bundle, Tamper::None, params),
+                SAPLING_BRANCH_ID + 1,
+                &bundle.keys,
+                params,
+            ),
+            Err(VerifyError::InvalidSpend(0))
+        );
+    }
+}